@@ -0,0 +1,76 @@
+CREATE TABLE IF NOT EXISTS users (
+    id INTEGER PRIMARY KEY,
+    username VARCHAR(10) NOT NULL,
+    password VARCHAR(255) NOT NULL,
+    nickname VARCHAR(10) NOT NULL,
+    email VARCHAR(100) NOT NULL,
+    bio VARCHAR(1000),
+    is_manager BOOLEAN NOT NULL,
+    is_withdrawn BOOLEAN NOT NULL,
+    field INTEGER,
+    profile_img BLOB
+);
+
+CREATE TABLE IF NOT EXISTS contests (
+    contest_id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    title VARCHAR(100) NOT NULL,
+    img VARCHAR(1000),
+    ratio VARCHAR(100) NOT NULL,
+    prize VARCHAR(100) NOT NULL,
+    started_at DATETIME NOT NULL,
+    ended_at DATETIME NOT NULL,
+    link VARCHAR(1000) NOT NULL,
+    field INTEGER NOT NULL,
+    like_count INTEGER NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+CREATE TABLE IF NOT EXISTS posts (
+    post_id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    contest_id INTEGER,
+    title VARCHAR(100) NOT NULL,
+    content VARCHAR(1000),
+    max INTEGER,
+    ppl INTEGER,
+    desired_field INTEGER,
+    created_at DATETIME NOT NULL,
+    ended_at DATETIME NOT NULL,
+    like_count INTEGER NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id),
+    FOREIGN KEY (contest_id) REFERENCES contests(contest_id)
+);
+
+CREATE TABLE IF NOT EXISTS comments (
+    comment_id INTEGER PRIMARY KEY,
+    post_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    content VARCHAR NOT NULL,
+    created_at DATETIME NOT NULL,
+    edited_at DATETIME,
+    parent INTEGER,
+    FOREIGN KEY (post_id) REFERENCES posts(post_id),
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    user_agent VARCHAR(1000),
+    ip VARCHAR(100),
+    created_at DATETIME NOT NULL,
+    last_seen_at DATETIME NOT NULL,
+    revoked BOOLEAN NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id INTEGER PRIMARY KEY,
+    session_id INTEGER NOT NULL,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    created_at DATETIME NOT NULL,
+    expires_at DATETIME NOT NULL,
+    rotated_at DATETIME,
+    FOREIGN KEY (session_id) REFERENCES sessions(id)
+);
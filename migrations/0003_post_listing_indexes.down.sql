@@ -0,0 +1,3 @@
+DROP INDEX posts_like_count;
+DROP INDEX posts_ended_at;
+DROP INDEX posts_created_at;
@@ -0,0 +1,3 @@
+CREATE INDEX posts_created_at ON posts (created_at, post_id);
+CREATE INDEX posts_ended_at ON posts (ended_at, post_id);
+CREATE INDEX posts_like_count ON posts (like_count, post_id);
@@ -0,0 +1,3 @@
+DROP INDEX contests_like_count;
+DROP INDEX contests_started_at;
+DROP INDEX contests_ended_at;
@@ -0,0 +1,3 @@
+CREATE INDEX contests_ended_at ON contests (ended_at, contest_id);
+CREATE INDEX contests_started_at ON contests (started_at, contest_id);
+CREATE INDEX contests_like_count ON contests (like_count, contest_id);
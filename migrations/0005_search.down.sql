@@ -0,0 +1,13 @@
+DROP TRIGGER comments_fts_update;
+DROP TRIGGER comments_fts_delete;
+DROP TRIGGER comments_fts_insert;
+DROP TRIGGER posts_fts_update;
+DROP TRIGGER posts_fts_delete;
+DROP TRIGGER posts_fts_insert;
+DROP TRIGGER contests_fts_update;
+DROP TRIGGER contests_fts_delete;
+DROP TRIGGER contests_fts_insert;
+
+DROP TABLE comments_fts;
+DROP TABLE posts_fts;
+DROP TABLE contests_fts;
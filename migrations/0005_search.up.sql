@@ -0,0 +1,57 @@
+-- The trigram tokenizer indexes every three-character substring, so Korean text, which has
+-- no reliable word boundaries for the default tokenizer, matches on partial words too.
+CREATE VIRTUAL TABLE contests_fts USING fts5(
+    title, prize,
+    content = 'contests', content_rowid = 'contest_id', tokenize = 'trigram'
+);
+CREATE VIRTUAL TABLE posts_fts USING fts5(
+    title, content,
+    content = 'posts', content_rowid = 'post_id', tokenize = 'trigram'
+);
+CREATE VIRTUAL TABLE comments_fts USING fts5(
+    content,
+    content = 'comments', content_rowid = 'comment_id', tokenize = 'trigram'
+);
+
+CREATE TRIGGER contests_fts_insert AFTER INSERT ON contests BEGIN
+    INSERT INTO contests_fts (rowid, title, prize) VALUES (new.contest_id, new.title, new.prize);
+END;
+CREATE TRIGGER contests_fts_delete AFTER DELETE ON contests BEGIN
+    INSERT INTO contests_fts (contests_fts, rowid, title, prize)
+    VALUES ('delete', old.contest_id, old.title, old.prize);
+END;
+CREATE TRIGGER contests_fts_update AFTER UPDATE OF title, prize ON contests BEGIN
+    INSERT INTO contests_fts (contests_fts, rowid, title, prize)
+    VALUES ('delete', old.contest_id, old.title, old.prize);
+    INSERT INTO contests_fts (rowid, title, prize) VALUES (new.contest_id, new.title, new.prize);
+END;
+
+CREATE TRIGGER posts_fts_insert AFTER INSERT ON posts BEGIN
+    INSERT INTO posts_fts (rowid, title, content) VALUES (new.post_id, new.title, new.content);
+END;
+CREATE TRIGGER posts_fts_delete AFTER DELETE ON posts BEGIN
+    INSERT INTO posts_fts (posts_fts, rowid, title, content)
+    VALUES ('delete', old.post_id, old.title, old.content);
+END;
+CREATE TRIGGER posts_fts_update AFTER UPDATE OF title, content ON posts BEGIN
+    INSERT INTO posts_fts (posts_fts, rowid, title, content)
+    VALUES ('delete', old.post_id, old.title, old.content);
+    INSERT INTO posts_fts (rowid, title, content) VALUES (new.post_id, new.title, new.content);
+END;
+
+CREATE TRIGGER comments_fts_insert AFTER INSERT ON comments BEGIN
+    INSERT INTO comments_fts (rowid, content) VALUES (new.comment_id, new.content);
+END;
+CREATE TRIGGER comments_fts_delete AFTER DELETE ON comments BEGIN
+    INSERT INTO comments_fts (comments_fts, rowid, content)
+    VALUES ('delete', old.comment_id, old.content);
+END;
+CREATE TRIGGER comments_fts_update AFTER UPDATE OF content ON comments BEGIN
+    INSERT INTO comments_fts (comments_fts, rowid, content)
+    VALUES ('delete', old.comment_id, old.content);
+    INSERT INTO comments_fts (rowid, content) VALUES (new.comment_id, new.content);
+END;
+
+INSERT INTO contests_fts (contests_fts) VALUES ('rebuild');
+INSERT INTO posts_fts (posts_fts) VALUES ('rebuild');
+INSERT INTO comments_fts (comments_fts) VALUES ('rebuild');
@@ -0,0 +1,2 @@
+DROP INDEX applications_live;
+DROP TABLE applications;
@@ -0,0 +1,17 @@
+CREATE TABLE applications (
+    application_id INTEGER PRIMARY KEY,
+    post_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    message VARCHAR(1000) NOT NULL,
+    -- One of pending, accepted, rejected or withdrawn.
+    status VARCHAR(10) NOT NULL,
+    created_at DATETIME NOT NULL,
+    decided_at DATETIME,
+    FOREIGN KEY (post_id) REFERENCES posts(post_id),
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+-- A user may apply again after withdrawing or being rejected, but holds at most one live
+-- application per post.
+CREATE UNIQUE INDEX applications_live ON applications (post_id, user_id)
+WHERE status IN ('pending', 'accepted');
@@ -0,0 +1,4 @@
+DROP INDEX team_members_user;
+DROP INDEX team_members_leader;
+DROP TABLE team_members;
+DROP TABLE teams;
@@ -0,0 +1,24 @@
+CREATE TABLE teams (
+    team_id INTEGER PRIMARY KEY,
+    -- The recruitment post the team came from. Cleared if the post is deleted later.
+    post_id INTEGER UNIQUE,
+    contest_id INTEGER,
+    name VARCHAR(100) NOT NULL,
+    created_at DATETIME NOT NULL,
+    FOREIGN KEY (post_id) REFERENCES posts(post_id),
+    FOREIGN KEY (contest_id) REFERENCES contests(contest_id)
+);
+
+CREATE TABLE team_members (
+    team_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    -- Either leader or member.
+    role VARCHAR(10) NOT NULL,
+    joined_at DATETIME NOT NULL,
+    PRIMARY KEY (team_id, user_id),
+    FOREIGN KEY (team_id) REFERENCES teams(team_id),
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+CREATE UNIQUE INDEX team_members_leader ON team_members (team_id) WHERE role = 'leader';
+CREATE INDEX team_members_user ON team_members (user_id);
@@ -0,0 +1,2 @@
+DROP INDEX likes_item;
+DROP TABLE likes;
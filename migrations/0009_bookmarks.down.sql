@@ -0,0 +1,2 @@
+DROP INDEX bookmarks_item;
+DROP TABLE bookmarks;
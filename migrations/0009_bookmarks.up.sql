@@ -0,0 +1,11 @@
+CREATE TABLE bookmarks (
+    user_id INTEGER NOT NULL,
+    -- Either contest or post. `item_id` refers to `contests` or `posts` accordingly.
+    item_kind VARCHAR(10) NOT NULL,
+    item_id INTEGER NOT NULL,
+    created_at DATETIME NOT NULL,
+    PRIMARY KEY (user_id, item_kind, item_id),
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+CREATE INDEX bookmarks_item ON bookmarks (item_kind, item_id);
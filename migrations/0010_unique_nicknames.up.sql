@@ -0,0 +1,2 @@
+-- Fails if existing rows share a nickname; rename them before migrating.
+CREATE UNIQUE INDEX users_nickname ON users (nickname);
@@ -0,0 +1,3 @@
+DROP TABLE avatars;
+ALTER TABLE users DROP COLUMN avatar_hash;
+ALTER TABLE users ADD COLUMN profile_img BLOB;
@@ -0,0 +1,13 @@
+-- `profile_img` was never written by any endpoint. Avatars are stored per size in `avatars`,
+-- and `avatar_hash` identifies the current one for URLs and ETags.
+ALTER TABLE users DROP COLUMN profile_img;
+ALTER TABLE users ADD COLUMN avatar_hash VARCHAR(64);
+
+CREATE TABLE avatars (
+    user_id INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    content_type VARCHAR(100) NOT NULL,
+    data BLOB NOT NULL,
+    PRIMARY KEY (user_id, size),
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
@@ -0,0 +1,11 @@
+DROP TABLE attachments;
+
+DROP INDEX contests_poster_key;
+ALTER TABLE contests DROP COLUMN poster_key;
+
+-- Avatars already moved into storage are not copied back and have to be uploaded again.
+DROP TABLE avatars;
+ALTER TABLE legacy_avatars RENAME TO avatars;
+UPDATE users SET avatar_hash = NULL WHERE id NOT IN (SELECT user_id FROM avatars);
+
+DROP TABLE files;
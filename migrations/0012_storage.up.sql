@@ -0,0 +1,40 @@
+-- Uploaded files live in the configured storage backend under content-addressed keys. `files`
+-- records every stored key so that files nothing points at any more can be cleaned up.
+CREATE TABLE files (
+    key VARCHAR(100) PRIMARY KEY,
+    content_type VARCHAR(100) NOT NULL,
+    size INTEGER NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+-- Avatars point at stored files instead of holding their bytes. SQL cannot move the old BLOBs
+-- into storage, so they are kept in `legacy_avatars` until the server moves them on startup
+-- (see `avatars::import_legacy_avatars`).
+ALTER TABLE avatars RENAME TO legacy_avatars;
+CREATE TABLE avatars (
+    user_id INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    key VARCHAR(100) NOT NULL,
+    PRIMARY KEY (user_id, size),
+    FOREIGN KEY (user_id) REFERENCES users(id),
+    FOREIGN KEY (key) REFERENCES files(key)
+);
+CREATE INDEX avatars_key ON avatars (key);
+
+-- An uploaded poster; `img` holds its URL so clients need not know about keys.
+ALTER TABLE contests ADD COLUMN poster_key VARCHAR(100) REFERENCES files(key);
+CREATE INDEX contests_poster_key ON contests (poster_key);
+
+CREATE TABLE attachments (
+    attachment_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    post_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    key VARCHAR(100) NOT NULL,
+    filename VARCHAR(255) NOT NULL,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (post_id) REFERENCES posts(post_id),
+    FOREIGN KEY (user_id) REFERENCES users(id),
+    FOREIGN KEY (key) REFERENCES files(key)
+);
+CREATE INDEX attachments_post ON attachments (post_id, attachment_id);
+CREATE INDEX attachments_key ON attachments (key);
@@ -0,0 +1,9 @@
+DROP INDEX contests_poster_thumbnail_key;
+DROP INDEX contests_poster_card_key;
+ALTER TABLE contests DROP COLUMN poster_thumbnail_key;
+ALTER TABLE contests DROP COLUMN poster_card_key;
+
+-- Contests without a ratio from the fixed set get their original one back.
+UPDATE contests SET legacy_ratio = ratio WHERE ratio IS NOT NULL;
+ALTER TABLE contests DROP COLUMN ratio;
+ALTER TABLE contests RENAME COLUMN legacy_ratio TO ratio;
@@ -0,0 +1,12 @@
+-- `ratio` becomes one of a fixed set that uploaded posters are checked against. Free-form
+-- values from before cannot be interpreted, so `ratio` is NULL for those contests until a ratio
+-- is chosen; the original text stays in `legacy_ratio`.
+ALTER TABLE contests RENAME COLUMN ratio TO legacy_ratio;
+ALTER TABLE contests ADD COLUMN ratio VARCHAR(10);
+UPDATE contests SET ratio = legacy_ratio WHERE legacy_ratio IN ('1:1', '3:4', '16:9');
+
+-- An uploaded poster is kept in three sizes; `poster_key` is the full one.
+ALTER TABLE contests ADD COLUMN poster_card_key VARCHAR(100) REFERENCES files(key);
+ALTER TABLE contests ADD COLUMN poster_thumbnail_key VARCHAR(100) REFERENCES files(key);
+CREATE INDEX contests_poster_card_key ON contests (poster_card_key);
+CREATE INDEX contests_poster_thumbnail_key ON contests (poster_thumbnail_key);
@@ -0,0 +1,5 @@
+-- Databases created by the old `init_tables` already had a `users` table, so uniqueness is added
+-- here rather than in 0001, giving fresh and upgraded databases the same indexes. Fails if
+-- existing rows share a username or email; resolve those before migrating.
+CREATE UNIQUE INDEX users_username ON users (username);
+CREATE UNIQUE INDEX users_email ON users (email);
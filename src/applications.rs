@@ -0,0 +1,160 @@
+use axum::{extract::State, http::StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::{ensure_owner_or_manager, Auth},
+    error::AppError,
+    extract::{Json, Path},
+    models::{AcceptResult, Application, ApplicationStatus, ApplicationWithUser},
+    posts::find_post,
+    utils::now,
+    AppState,
+};
+
+async fn find_application(
+    state: &AppState,
+    post_id: i32,
+    application_id: i32,
+) -> Result<Application, AppError> {
+    match Application::find_by_id(&state.pool, application_id).await? {
+        Some(application) if application.post_id == post_id => Ok(application),
+        _ => Err(AppError::not_found("Application not found")),
+    }
+}
+
+fn not_pending() -> AppError {
+    AppError::conflict("Application is no longer pending")
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyBody {
+    message: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyResponse {
+    application_id: i32,
+}
+
+pub async fn apply(
+    State(state): State<AppState>,
+    Path(post_id): Path<i32>,
+    Auth(auth): Auth,
+    Json(body): Json<ApplyBody>,
+) -> Result<(StatusCode, Json<ApplyResponse>), AppError> {
+    let post = find_post(&state, post_id).await?;
+    if post.user_id == auth.sub {
+        return Err(AppError::unprocessable("Cannot apply to your own post"));
+    }
+    let now = now();
+    if post.ended_at <= now || post.ppl >= post.max {
+        return Err(AppError::conflict("Post is no longer recruiting"));
+    }
+
+    // The `applications_live` index rejects a second live application from the same user,
+    // even when two requests race.
+    let application_id = Application::insert(
+        &state.pool,
+        &Application {
+            post_id,
+            user_id: auth.sub,
+            message: body.message,
+            status: ApplicationStatus::Pending,
+            created_at: now,
+            ..Default::default()
+        },
+    )
+    .await
+    .map_err(|e| match AppError::from(e) {
+        AppError::Conflict(_, details) => {
+            AppError::Conflict("You already applied to this post".into(), details)
+        }
+        e => e,
+    })? as _;
+    Ok((StatusCode::CREATED, Json(ApplyResponse { application_id })))
+}
+
+pub async fn list_applications(
+    State(state): State<AppState>,
+    Path(post_id): Path<i32>,
+    Auth(auth): Auth,
+) -> Result<Json<Vec<ApplicationWithUser>>, AppError> {
+    let post = find_post(&state, post_id).await?;
+    ensure_owner_or_manager(&state, &auth, post.user_id).await?;
+
+    Ok(Json(
+        Application::find_by_post_id(&state.pool, post_id).await?,
+    ))
+}
+
+pub async fn withdraw_application(
+    State(state): State<AppState>,
+    Path((post_id, application_id)): Path<(i32, i32)>,
+    Auth(auth): Auth,
+) -> Result<StatusCode, AppError> {
+    let application = find_application(&state, post_id, application_id).await?;
+    if application.user_id != auth.sub {
+        return Err(AppError::forbidden(
+            "Only the applicant may withdraw an application",
+        ));
+    }
+
+    if !Application::decide(
+        &state.pool,
+        application_id,
+        ApplicationStatus::Withdrawn,
+        now(),
+    )
+    .await?
+    {
+        return Err(not_pending());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn accept_application(
+    State(state): State<AppState>,
+    Path((post_id, application_id)): Path<(i32, i32)>,
+    Auth(auth): Auth,
+) -> Result<Json<Application>, AppError> {
+    let post = find_post(&state, post_id).await?;
+    ensure_owner_or_manager(&state, &auth, post.user_id).await?;
+    let mut application = find_application(&state, post_id, application_id).await?;
+
+    let decided_at = now();
+    match Application::accept(&state.pool, &application, decided_at).await? {
+        AcceptResult::Accepted => {}
+        AcceptResult::PostFull => return Err(AppError::conflict("Post is already full")),
+        AcceptResult::NotPending => return Err(not_pending()),
+    }
+    application.status = ApplicationStatus::Accepted;
+    application.decided_at = Some(decided_at);
+    Ok(Json(application))
+}
+
+pub async fn reject_application(
+    State(state): State<AppState>,
+    Path((post_id, application_id)): Path<(i32, i32)>,
+    Auth(auth): Auth,
+) -> Result<Json<Application>, AppError> {
+    let post = find_post(&state, post_id).await?;
+    ensure_owner_or_manager(&state, &auth, post.user_id).await?;
+    let mut application = find_application(&state, post_id, application_id).await?;
+
+    let decided_at = now();
+    if !Application::decide(
+        &state.pool,
+        application_id,
+        ApplicationStatus::Rejected,
+        decided_at,
+    )
+    .await?
+    {
+        return Err(not_pending());
+    }
+    application.status = ApplicationStatus::Rejected;
+    application.decided_at = Some(decided_at);
+    Ok(Json(application))
+}
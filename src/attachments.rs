@@ -0,0 +1,136 @@
+use axum::{extract::State, http::StatusCode};
+use serde::Serialize;
+
+use crate::{
+    auth::{ensure_owner_or_manager, Auth},
+    error::AppError,
+    extract::{Json, Multipart, Path},
+    files::{self, file_url},
+    models::Attachment,
+    posts::find_post,
+    utils::now,
+    AppState,
+};
+
+const FILENAME_MAX: usize = 255;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentResponse {
+    #[serde(flatten)]
+    attachment: Attachment,
+    url: String,
+}
+
+impl From<Attachment> for AttachmentResponse {
+    fn from(attachment: Attachment) -> AttachmentResponse {
+        AttachmentResponse {
+            url: file_url(&attachment.key),
+            attachment,
+        }
+    }
+}
+
+/// Keeps the declared content type if it looks like `type/subtype`. It only decides how the
+/// file is served, and anything but a few image types is served as a download anyway.
+fn content_type(declared: Option<&str>) -> String {
+    let valid = |part: &str| {
+        !part.is_empty()
+            && part
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"+-.".contains(&b))
+    };
+    match declared.map(str::to_ascii_lowercase) {
+        Some(declared)
+            if declared
+                .split_once('/')
+                .is_some_and(|(kind, subtype)| valid(kind) && valid(subtype)) =>
+        {
+            declared
+        }
+        _ => "application/octet-stream".to_string(),
+    }
+}
+
+/// The last path component of the uploaded file's name, shortened to [`FILENAME_MAX`]
+/// characters.
+fn filename(declared: Option<&str>) -> String {
+    let name = declared
+        .and_then(|name| name.rsplit(['/', '\\']).next())
+        .map(str::trim)
+        .unwrap_or_default();
+    if name.is_empty() {
+        "file".to_string()
+    } else {
+        name.chars().take(FILENAME_MAX).collect()
+    }
+}
+
+/// Attaches the file in the `file` field of a multipart body to the post.
+pub async fn upload_attachment(
+    State(state): State<AppState>,
+    Path(post_id): Path<i32>,
+    Auth(auth): Auth,
+    Multipart(mut multipart): Multipart,
+) -> Result<(StatusCode, Json<AttachmentResponse>), AppError> {
+    let post = find_post(&state, post_id).await?;
+    ensure_owner_or_manager(&state, &auth, post.user_id).await?;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::unprocessable(e.body_text()))?
+    {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let content_type = content_type(field.content_type());
+        let filename = filename(field.file_name());
+        let data = files::read_field(field, state.config.upload.max_bytes).await?;
+
+        let mut attachment = Attachment {
+            post_id,
+            user_id: auth.sub,
+            filename,
+            created_at: now(),
+            size: data.len() as i64,
+            key: state.files.store(data, &content_type).await?,
+            content_type,
+            ..Default::default()
+        };
+        attachment.attachment_id = Attachment::insert(&state.pool, &attachment).await? as i32;
+        return Ok((StatusCode::CREATED, Json(attachment.into())));
+    }
+    Err(AppError::unprocessable("Missing `file` field"))
+}
+
+pub async fn list_attachments(
+    State(state): State<AppState>,
+    Path(post_id): Path<i32>,
+) -> Result<Json<Vec<AttachmentResponse>>, AppError> {
+    find_post(&state, post_id).await?;
+    Ok(Json(
+        Attachment::find_by_post_id(&state.pool, post_id)
+            .await?
+            .into_iter()
+            .map(AttachmentResponse::from)
+            .collect(),
+    ))
+}
+
+/// Removes the attachment. The file itself goes once orphan cleanup finds nothing else uses it.
+pub async fn delete_attachment(
+    State(state): State<AppState>,
+    Path((post_id, attachment_id)): Path<(i32, i32)>,
+    Auth(auth): Auth,
+) -> Result<StatusCode, AppError> {
+    let post = find_post(&state, post_id).await?;
+    ensure_owner_or_manager(&state, &auth, post.user_id).await?;
+    match Attachment::find_by_id(&state.pool, attachment_id).await? {
+        Some(attachment) if attachment.post_id == post_id => {}
+        _ => return Err(AppError::not_found("Attachment not found")),
+    }
+
+    Attachment::delete(&state.pool, attachment_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,15 +1,25 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::net::SocketAddr;
 
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
 use axum::{
-    extract::{FromRequestParts, State},
-    http::{request::Parts, HeaderValue, StatusCode},
-    response::IntoResponse,
-    Json,
+    extract::{ConnectInfo, FromRequestParts, State},
+    http::{header::USER_AGENT, request::Parts, HeaderMap, HeaderValue, StatusCode},
 };
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
 
-use crate::{models::User, AppState};
+use crate::{
+    error::AppError,
+    extract::Json,
+    jwt::Claims,
+    models::{RefreshToken, Session, User},
+    users::{PublicUser, Validation, NICKNAME_MAX, USERNAME_MAX},
+    utils::{now, random_token, sha256_hex},
+    AppState,
+};
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -18,32 +28,69 @@ pub struct SignupBody {
     password: String,
     nickname: String,
     email: String,
+    #[serde(default)]
+    field: i32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignupResponse {
+    user: PublicUser,
+    #[serde(flatten)]
+    tokens: LoginResponse,
 }
 
 pub async fn signup(
     State(state): State<AppState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
     Json(body): Json<SignupBody>,
-) -> impl IntoResponse {
-    if User::find_by_username(&state.pool, &body.username)
-        .await
-        .is_some()
-    {
-        return (StatusCode::CONFLICT, "User already exists").into_response();
+) -> Result<(StatusCode, Json<SignupResponse>), AppError> {
+    let username = Validation::normalize_name(&body.username);
+    let nickname = Validation::normalize_name(&body.nickname);
+    let email = Validation::normalize_email(&body.email);
+
+    let mut validation = Validation::default();
+    validation.text("username", &username, USERNAME_MAX);
+    validation.text("nickname", &nickname, NICKNAME_MAX);
+    validation.email("email", &email);
+    if body.password.is_empty() {
+        validation.fail("password", "must not be empty".to_string());
     }
+    validation.finish()?;
 
-    User::insert(
-        &state.pool,
-        &User {
-            username: body.username,
-            password: body.password,
-            nickname: body.nickname,
-            email: body.email,
-            ..Default::default()
-        },
-    )
-    .await;
+    let user = User {
+        username,
+        password: hash_password(&body.password)?,
+        nickname,
+        email,
+        field: body.field,
+        ..Default::default()
+    };
 
-    StatusCode::CREATED.into_response()
+    // The unique indexes on `username`, `nickname` and `email` decide conflicts, so concurrent
+    // signups for the same name cannot both succeed.
+    let user_id = User::insert(&state.pool, &user)
+        .await
+        .map_err(|e| match AppError::from(e) {
+            AppError::Conflict(_, details) => AppError::Conflict(
+                "Username, nickname or email is already taken".into(),
+                details,
+            ),
+            e => e,
+        })? as i32;
+
+    let tokens = start_session(&state, user_id, connect_info, &headers).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(SignupResponse {
+            user: PublicUser::from(User {
+                id: user_id,
+                ..user
+            }),
+            tokens,
+        }),
+    ))
 }
 
 #[derive(Deserialize)]
@@ -57,74 +104,263 @@ pub struct LoginBody {
 #[serde(rename_all = "camelCase")]
 pub struct LoginResponse {
     token: String,
+    /// Access token lifetime in seconds.
+    expires_in: i64,
+    refresh_token: String,
 }
 
 pub async fn login(
     State(state): State<AppState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
     Json(body): Json<LoginBody>,
-) -> impl IntoResponse {
-    let Some(user) = User::find_by_username(&state.pool, &body.username).await else {
-        return StatusCode::UNAUTHORIZED.into_response();
+) -> Result<Json<LoginResponse>, AppError> {
+    let username = Validation::normalize_name(&body.username);
+    let Some(user) = User::find_by_username(&state.pool, &username).await? else {
+        return Err(AppError::unauthorized("Invalid username or password"));
     };
 
-    if user.password != body.password {
-        return StatusCode::UNAUTHORIZED.into_response();
+    match verify_password(&body.password, &user.password) {
+        PasswordMatch::Valid => {}
+        PasswordMatch::Legacy => {
+            // Rows created before hashing was introduced still hold the plaintext password.
+            let password = hash_password(&body.password)?;
+            User::update_password(&state.pool, user.id, &password).await?;
+        }
+        PasswordMatch::Invalid => {
+            return Err(AppError::unauthorized("Invalid username or password"))
+        }
     }
 
-    let token = create_jwt(user.id, b"secret").unwrap();
-    Json(LoginResponse { token }).into_response()
+    Ok(Json(
+        start_session(&state, user.id, connect_info, &headers).await?,
+    ))
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
-pub struct Claims {
-    pub sub: i32, // Subject (whom the token refers to)
-    // pub exp: usize,
-    pub iat: usize,
+/// Records a new session for `user_id` and issues its first token pair.
+async fn start_session(
+    state: &AppState,
+    user_id: i32,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: &HeaderMap,
+) -> Result<LoginResponse, AppError> {
+    let created_at = now();
+    let session_id = Session::insert(
+        &state.pool,
+        &Session {
+            user_id,
+            user_agent: headers
+                .get(USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            ip: connect_info.map(|ConnectInfo(addr)| addr.ip().to_string()),
+            created_at,
+            last_seen_at: created_at,
+            ..Default::default()
+        },
+    )
+    .await? as _;
+
+    issue_tokens(state, user_id, session_id).await
 }
 
-fn create_jwt(user_id: i32, secret: &[u8]) -> Result<String, jsonwebtoken::errors::Error> {
-    // let expiration = SystemTime::now()
-    //     .duration_since(UNIX_EPOCH)
-    //     .unwrap()
-    //     .as_secs()
-    //     + 3600; // Token expires in 1 hour
+/// Signs an access token and stores a new refresh token for `session_id`.
+async fn issue_tokens(
+    state: &AppState,
+    user_id: i32,
+    session_id: i32,
+) -> Result<LoginResponse, AppError> {
+    let token = state.jwt.encode(user_id, session_id)?;
+    let refresh_token = random_token();
+    let created_at = now();
 
-    let claims = Claims {
-        sub: user_id,
-        // exp: expiration as usize,
-        iat: SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as usize,
-    };
+    RefreshToken::insert(
+        &state.pool,
+        &RefreshToken {
+            session_id,
+            token_hash: sha256_hex(&refresh_token),
+            created_at,
+            expires_at: created_at + state.jwt.refresh_lifetime(),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(LoginResponse {
+        token,
+        expires_in: state.jwt.lifetime(),
+        refresh_token,
+    })
+}
 
-    let header = Header::default();
-    encode(&header, &claims, &EncodingKey::from_secret(secret))
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshBody {
+    refresh_token: String,
+}
+
+pub async fn refresh(
+    State(state): State<AppState>,
+    Json(body): Json<RefreshBody>,
+) -> Result<Json<LoginResponse>, AppError> {
+    let invalid = || AppError::unauthorized("Invalid refresh token");
+
+    let token = RefreshToken::find_by_hash(&state.pool, &sha256_hex(&body.refresh_token))
+        .await?
+        .ok_or_else(invalid)?;
+    let session = Session::find_by_id(&state.pool, token.session_id)
+        .await?
+        .ok_or_else(invalid)?;
+
+    if session.revoked || token.expires_at <= now() {
+        return Err(invalid());
+    }
+
+    if !RefreshToken::mark_rotated(&state.pool, token.id, now()).await? {
+        // A rotated token was presented again, so it has leaked. End the whole session.
+        Session::revoke(&state.pool, session.id).await?;
+        return Err(AppError::unauthorized("Refresh token reused"));
+    }
+
+    Session::touch(&state.pool, session.id, now()).await?;
+    Ok(Json(
+        issue_tokens(&state, session.user_id, session.id).await?,
+    ))
+}
+
+pub async fn logout(
+    State(state): State<AppState>,
+    Json(body): Json<RefreshBody>,
+) -> Result<StatusCode, AppError> {
+    if let Some(token) =
+        RefreshToken::find_by_hash(&state.pool, &sha256_hex(&body.refresh_token)).await?
+    {
+        Session::revoke(&state.pool, token.session_id).await?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Hashes `password` with Argon2id and a fresh random salt, returning a PHC string.
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)?
+        .to_string())
+}
+
+pub enum PasswordMatch {
+    Valid,
+    /// The stored value is not a PHC hash but equals the given password verbatim.
+    Legacy,
+    Invalid,
+}
+
+pub fn verify_password(password: &str, stored: &str) -> PasswordMatch {
+    match PasswordHash::new(stored) {
+        Ok(hash) => match Argon2::default().verify_password(password.as_bytes(), &hash) {
+            Ok(()) => PasswordMatch::Valid,
+            Err(_) => PasswordMatch::Invalid,
+        },
+        // Constant-time, so response times do not reveal how much of a legacy password matched.
+        Err(_) if bool::from(stored.as_bytes().ct_eq(password.as_bytes())) => PasswordMatch::Legacy,
+        Err(_) => PasswordMatch::Invalid,
+    }
 }
 
 pub struct Auth(pub Claims);
 
 #[async_trait::async_trait]
 impl FromRequestParts<AppState> for Auth {
-    type Rejection = (StatusCode, &'static str);
+    type Rejection = AppError;
 
-    async fn from_request_parts(parts: &mut Parts, _: &AppState) -> Result<Self, Self::Rejection> {
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
         let Some(Ok(value)) = parts.headers.get("authorization").map(HeaderValue::to_str) else {
-            return Err((StatusCode::UNAUTHORIZED, "Invalid `Authorization` header"));
+            return Err(AppError::unauthorized("Invalid `Authorization` header"));
+        };
+
+        let Some(token) = value.split(' ').next_back() else {
+            return Err(AppError::unauthorized("Invalid `Authorization` header"));
         };
 
-        let Some(token) = value.split(' ').last() else {
-            return Err((StatusCode::UNAUTHORIZED, "Invalid `Authorization` header"));
+        let Some(claims) = state.jwt.decode(token) else {
+            return Err(AppError::unauthorized("Invalid token"));
         };
 
-        let mut validation = Validation::default();
-        validation.required_spec_claims.clear();
-        validation.validate_exp = false;
-        let key = DecodingKey::from_secret(b"secret");
-        let Ok(token_data) = decode(token, &key, &validation) else {
-            return Err((StatusCode::UNAUTHORIZED, "Invalid token"));
+        let Some(session) = Session::find_by_id(&state.pool, claims.sid).await? else {
+            return Err(AppError::unauthorized("Invalid token"));
         };
 
-        Ok(Auth(token_data.claims))
+        if session.revoked || session.user_id != claims.sub {
+            return Err(AppError::unauthorized("Session revoked"));
+        }
+
+        let now = now();
+        // Only write when the value is noticeably stale to avoid a write on every request.
+        if now - session.last_seen_at >= 60 {
+            Session::touch(&state.pool, session.id, now).await?;
+        }
+
+        Ok(Auth(claims))
+    }
+}
+
+/// Like [`Auth`], but lets anonymous requests through as `None`. A request that does send an
+/// `Authorization` header must still carry a valid token.
+pub struct MaybeAuth(pub Option<Claims>);
+
+#[async_trait::async_trait]
+impl FromRequestParts<AppState> for MaybeAuth {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        if !parts.headers.contains_key("authorization") {
+            return Ok(MaybeAuth(None));
+        }
+        let Auth(claims) = Auth::from_request_parts(parts, state).await?;
+        Ok(MaybeAuth(Some(claims)))
+    }
+}
+
+/// Succeeds if `claims` belongs to `owner_id` or to a manager, the two kinds of users allowed to
+/// edit or delete someone's post, contest or comment.
+pub async fn ensure_owner_or_manager(
+    state: &AppState,
+    claims: &Claims,
+    owner_id: i32,
+) -> Result<(), AppError> {
+    if claims.sub == owner_id {
+        return Ok(());
+    }
+    match User::find_by_id(&state.pool, claims.sub).await? {
+        Some(user) if user.is_manager && !user.is_withdrawn => Ok(()),
+        _ => Err(AppError::forbidden(
+            "Only the author or a manager may do this",
+        )),
+    }
+}
+
+/// Like [`Auth`], but only admits users with `is_manager` set.
+pub struct RequireManager(pub Claims);
+
+#[async_trait::async_trait]
+impl FromRequestParts<AppState> for RequireManager {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Auth(claims) = Auth::from_request_parts(parts, state).await?;
+
+        match User::find_by_id(&state.pool, claims.sub).await? {
+            Some(user) if user.is_manager && !user.is_withdrawn => Ok(RequireManager(claims)),
+            _ => Err(AppError::forbidden("Manager role required")),
+        }
     }
 }
@@ -0,0 +1,155 @@
+use axum::{
+    extract::State,
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use crate::{
+    auth::Auth,
+    error::AppError,
+    extract::{Json, Multipart, Path, Query},
+    files, images,
+    models::{Avatar, LegacyAvatar, User},
+    users::PrivateUser,
+    utils::sha256_hex,
+    AppState,
+};
+
+/// Square sizes every avatar is stored in, in pixels. The first is served by default.
+const AVATAR_SIZES: [u32; 2] = [256, 64];
+
+/// URL of the current avatar for JSON responses. The hash in the query string changes with
+/// every upload, so clients never show a stale cached image.
+pub fn avatar_url(user_id: i32, hash: &str) -> String {
+    format!("/users/{user_id}/avatar?v={}", &hash[..16])
+}
+
+/// Takes the image from the `avatar` field of a multipart body, crops it square and stores it
+/// in every size of [`AVATAR_SIZES`].
+pub async fn upload_avatar(
+    State(state): State<AppState>,
+    Auth(claims): Auth,
+    Multipart(mut multipart): Multipart,
+) -> Result<Json<PrivateUser>, AppError> {
+    let mut bytes = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::unprocessable(e.body_text()))?
+    {
+        if field.name() == Some("avatar") {
+            bytes = Some(files::read_field(field, state.config.upload.max_image_bytes).await?);
+            break;
+        }
+    }
+    let Some(bytes) = bytes else {
+        return Err(AppError::unprocessable("Missing `avatar` file field"));
+    };
+
+    let hash = sha256_hex(&bytes);
+    let encoded = tokio::task::spawn_blocking(move || {
+        let image = images::decode(&bytes)?;
+        AVATAR_SIZES
+            .iter()
+            .map(|&size| Ok((size, images::encode(&images::square(&image, size))?)))
+            .collect::<Result<Vec<_>, AppError>>()
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("avatar task failed: {e}")))??;
+
+    let mut avatars = Vec::new();
+    for (size, (data, content_type)) in encoded {
+        avatars.push(Avatar {
+            user_id: claims.sub,
+            size: size as i32,
+            key: state.files.store(data, content_type).await?,
+            content_type: content_type.to_string(),
+        });
+    }
+    Avatar::replace(&state.pool, claims.sub, &hash, &avatars).await?;
+    match User::find_by_id(&state.pool, claims.sub).await? {
+        Some(user) => Ok(Json(user.into())),
+        None => Err(AppError::not_found("User not found")),
+    }
+}
+
+/// Moves avatars that migration 0012 left in `legacy_avatars` into the storage backend and
+/// returns how many users had one. Users keep their `avatar_hash`, so URLs and ETags stay valid.
+pub async fn import_legacy_avatars(state: &AppState) -> Result<usize, AppError> {
+    let user_ids = LegacyAvatar::find_user_ids(&state.pool).await?;
+    for &user_id in &user_ids {
+        let mut avatars = Vec::new();
+        for legacy in LegacyAvatar::find_by_user_id(&state.pool, user_id).await? {
+            avatars.push(Avatar {
+                user_id: legacy.user_id,
+                size: legacy.size,
+                key: state.files.store(legacy.data, &legacy.content_type).await?,
+                content_type: legacy.content_type,
+            });
+        }
+        LegacyAvatar::migrate(&state.pool, user_id, &avatars).await?;
+    }
+    Ok(user_ids.len())
+}
+
+pub async fn delete_avatar(
+    State(state): State<AppState>,
+    Auth(claims): Auth,
+) -> Result<StatusCode, AppError> {
+    Avatar::delete(&state.pool, claims.sub).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct AvatarQuery {
+    size: Option<u32>,
+}
+
+pub async fn get_avatar(
+    State(state): State<AppState>,
+    Path(user_id): Path<i32>,
+    Query(query): Query<AvatarQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let size = query.size.unwrap_or(AVATAR_SIZES[0]);
+    if !AVATAR_SIZES.contains(&size) {
+        return Err(AppError::unprocessable(format!(
+            "`size` must be one of {AVATAR_SIZES:?}"
+        )));
+    }
+    let Some(hash) = User::find_by_id(&state.pool, user_id)
+        .await?
+        .and_then(|user| user.avatar_hash)
+    else {
+        return Err(AppError::not_found("Avatar not found"));
+    };
+
+    let etag = format!("\"{hash}-{size}\"");
+    // Revalidate on every use; a matching ETag makes that a cheap 304.
+    let cache = [
+        (ETAG, etag.clone()),
+        (CACHE_CONTROL, "no-cache".to_string()),
+    ];
+    let matches = headers
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag));
+    if matches {
+        return Ok((StatusCode::NOT_MODIFIED, cache).into_response());
+    }
+
+    let Some(avatar) = Avatar::find(&state.pool, user_id, size as i32).await? else {
+        return Err(AppError::not_found("Avatar not found"));
+    };
+    match state.files.load(&avatar.key).await? {
+        Some(data) => Ok((cache, [(CONTENT_TYPE, avatar.content_type)], data).into_response()),
+        None => Err(AppError::Internal(format!(
+            "avatar file {} is missing from storage",
+            avatar.key
+        ))),
+    }
+}
@@ -0,0 +1,82 @@
+use axum::{extract::State, http::StatusCode};
+use serde::Serialize;
+
+use crate::{
+    auth::Auth,
+    contests::{contest_responses, ContestResponse},
+    error::AppError,
+    extract::{Json, Path},
+    models::{Bookmark, ItemKind},
+    posts::{post_responses, PostResponse},
+    utils::now,
+    AppState,
+};
+
+async fn set_bookmark(
+    state: &AppState,
+    user_id: i32,
+    kind: ItemKind,
+    item_id: i32,
+    bookmarked: bool,
+) -> Result<StatusCode, AppError> {
+    if Bookmark::set(&state.pool, user_id, kind, item_id, bookmarked, now()).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::not_found(match kind {
+            ItemKind::Contest => "Contest not found",
+            ItemKind::Post => "Post not found",
+        }))
+    }
+}
+
+pub async fn bookmark_contest(
+    State(state): State<AppState>,
+    Path(contest_id): Path<i32>,
+    Auth(auth): Auth,
+) -> Result<StatusCode, AppError> {
+    set_bookmark(&state, auth.sub, ItemKind::Contest, contest_id, true).await
+}
+
+pub async fn unbookmark_contest(
+    State(state): State<AppState>,
+    Path(contest_id): Path<i32>,
+    Auth(auth): Auth,
+) -> Result<StatusCode, AppError> {
+    set_bookmark(&state, auth.sub, ItemKind::Contest, contest_id, false).await
+}
+
+pub async fn bookmark_post(
+    State(state): State<AppState>,
+    Path(post_id): Path<i32>,
+    Auth(auth): Auth,
+) -> Result<StatusCode, AppError> {
+    set_bookmark(&state, auth.sub, ItemKind::Post, post_id, true).await
+}
+
+pub async fn unbookmark_post(
+    State(state): State<AppState>,
+    Path(post_id): Path<i32>,
+    Auth(auth): Auth,
+) -> Result<StatusCode, AppError> {
+    set_bookmark(&state, auth.sub, ItemKind::Post, post_id, false).await
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookmarksResponse {
+    contests: Vec<ContestResponse>,
+    posts: Vec<PostResponse>,
+}
+
+/// Lists the caller's saved contests and posts, most recently saved first.
+pub async fn list_bookmarks(
+    State(state): State<AppState>,
+    Auth(auth): Auth,
+) -> Result<Json<BookmarksResponse>, AppError> {
+    let contests = Bookmark::find_contests(&state.pool, auth.sub).await?;
+    let posts = Bookmark::find_posts(&state.pool, auth.sub).await?;
+    Ok(Json(BookmarksResponse {
+        contests: contest_responses(&state, Some(&auth), contests).await?,
+        posts: post_responses(&state, Some(&auth), posts).await?,
+    }))
+}
@@ -1,20 +1,208 @@
-use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
-    Json,
-};
+use std::collections::HashMap;
+
+use axum::{extract::State, http::StatusCode};
 use serde::{Deserialize, Serialize};
 
-use crate::{auth::Auth, models::Comment, utils::now, AppState};
+use crate::{
+    auth::{ensure_owner_or_manager, Auth},
+    error::AppError,
+    extract::{Json, Path, Query},
+    models::{Comment, CommentWithUser},
+    utils::now,
+    AppState,
+};
+
+/// Nesting depth used by `?format=tree` when `maxDepth` is not given.
+const DEFAULT_MAX_DEPTH: usize = 5;
+/// Largest `maxDepth` honored. Deeper replies are flattened onto the last level.
+const MAX_DEPTH_LIMIT: usize = 20;
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentFormat {
+    #[default]
+    Flat,
+    Tree,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListCommentsQuery {
+    #[serde(default)]
+    format: CommentFormat,
+    max_depth: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentNode {
+    #[serde(flatten)]
+    comment: CommentWithUser,
+    /// Number of comments anywhere below this one.
+    reply_count: usize,
+    replies: Vec<CommentNode>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum ListCommentsResponse {
+    Flat(Vec<CommentWithUser>),
+    Tree(Vec<CommentNode>),
+}
 
 pub async fn list_comments(
     State(state): State<AppState>,
     Path(post_id): Path<i32>,
-) -> impl IntoResponse {
-    let mut comments = Comment::find_by_post_id(&state.pool, post_id).await;
-    comments.reverse();
-    Json(comments)
+    Query(query): Query<ListCommentsQuery>,
+) -> Result<Json<ListCommentsResponse>, AppError> {
+    let mut comments = Comment::find_by_post_id(&state.pool, post_id).await?;
+    match query.format {
+        CommentFormat::Flat => {
+            comments.reverse();
+            Ok(Json(ListCommentsResponse::Flat(comments)))
+        }
+        CommentFormat::Tree => {
+            let max_depth = query
+                .max_depth
+                .unwrap_or(DEFAULT_MAX_DEPTH)
+                .clamp(1, MAX_DEPTH_LIMIT);
+            Ok(Json(ListCommentsResponse::Tree(build_tree(
+                comments, max_depth,
+            ))))
+        }
+    }
+}
+
+/// Nests `comments` under their parents. Top-level comments come newest first and replies in
+/// the order they were written. Replies that would sit deeper than `max_depth` are attached to
+/// their ancestor on the last allowed level instead.
+///
+/// Threads can be arbitrarily deep, so this walks them iteratively rather than recursing.
+fn build_tree(mut comments: Vec<CommentWithUser>, max_depth: usize) -> Vec<CommentNode> {
+    comments.sort_by_key(|comment| comment.comment_id);
+    let index = comments
+        .iter()
+        .enumerate()
+        .map(|(i, comment)| (comment.comment_id, i))
+        .collect::<HashMap<_, _>>();
+    // Replies whose parent is gone are shown at the top level rather than dropped.
+    let mut parents = comments
+        .iter()
+        .map(|comment| {
+            comment
+                .parent
+                .and_then(|parent| index.get(&parent).copied())
+        })
+        .collect::<Vec<_>>();
+    break_cycles(&mut parents);
+
+    let mut children = vec![Vec::new(); comments.len()];
+    let mut roots = Vec::new();
+    for (i, parent) in parents.iter().enumerate() {
+        match parent {
+            Some(parent) => children[*parent].push(i),
+            None => roots.push(i),
+        }
+    }
+
+    // Breadth-first, so every comment comes after its parent.
+    let mut order = roots.clone();
+    let mut depths = vec![0; comments.len()];
+    let mut next = 0;
+    while let Some(&i) = order.get(next) {
+        next += 1;
+        for &child in &children[i] {
+            depths[child] = depths[i] + 1;
+            order.push(child);
+        }
+    }
+
+    let mut reply_counts = vec![0; comments.len()];
+    for &i in order.iter().rev() {
+        if let Some(parent) = parents[i] {
+            reply_counts[parent] += reply_counts[i] + 1;
+        }
+    }
+
+    // Comments below the last allowed level, grouped by their ancestor on that level.
+    let last_level = max_depth - 1;
+    let mut anchors = vec![None; comments.len()];
+    let mut flattened = vec![Vec::new(); comments.len()];
+    for &i in &order {
+        let Some(parent) = parents[i].filter(|_| depths[i] > last_level) else {
+            continue;
+        };
+        let anchor = if depths[parent] == last_level {
+            parent
+        } else {
+            anchors[parent].expect("ancestors below the last level have an anchor")
+        };
+        anchors[i] = Some(anchor);
+        flattened[anchor].push(i);
+    }
+
+    let mut comments = comments.into_iter().map(Some).collect::<Vec<_>>();
+    let mut nodes = (0..comments.len()).map(|_| None).collect::<Vec<_>>();
+    for &i in order.iter().rev().filter(|&&i| depths[i] <= last_level) {
+        let replies = if depths[i] < last_level {
+            children[i]
+                .iter()
+                .map(|&child| nodes[child].take().expect("replies are built first"))
+                .collect()
+        } else {
+            flattened[i].sort_unstable();
+            flattened[i]
+                .iter()
+                .map(|&j| CommentNode {
+                    comment: comments[j].take().expect("each comment is used once"),
+                    reply_count: 0,
+                    replies: Vec::new(),
+                })
+                .collect()
+        };
+        nodes[i] = Some(CommentNode {
+            comment: comments[i].take().expect("each comment is used once"),
+            reply_count: reply_counts[i],
+            replies,
+        });
+    }
+
+    roots
+        .into_iter()
+        .rev()
+        .map(|i| nodes[i].take().expect("roots are built"))
+        .collect()
+}
+
+/// Turns every comment on a parent cycle, which old rows can contain, into a top-level one so
+/// it and its replies still reach a root.
+fn break_cycles(parents: &mut [Option<usize>]) {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Visit {
+        New,
+        OnPath,
+        Done,
+    }
+
+    let mut visits = vec![Visit::New; parents.len()];
+    let mut path = Vec::new();
+    for start in 0..parents.len() {
+        let mut i = Some(start);
+        while let Some(current) = i.filter(|&current| visits[current] == Visit::New) {
+            visits[current] = Visit::OnPath;
+            path.push(current);
+            i = parents[current];
+        }
+        if let Some(repeated) = i.filter(|&repeated| visits[repeated] == Visit::OnPath) {
+            let cycle = path.iter().position(|&j| j == repeated).unwrap();
+            for &j in &path[cycle..] {
+                parents[j] = None;
+            }
+        }
+        for j in path.drain(..) {
+            visits[j] = Visit::Done;
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -35,7 +223,18 @@ pub async fn create_comment(
     Path(post_id): Path<i32>,
     Auth(auth): Auth,
     Json(body): Json<CreateCommentBody>,
-) -> impl IntoResponse {
+) -> Result<(StatusCode, Json<CreateCommentResponse>), AppError> {
+    if let Some(parent) = body.parent {
+        match Comment::find_by_id(&state.pool, parent).await? {
+            Some(parent) if parent.post_id == post_id && !parent.is_deleted => {}
+            _ => {
+                return Err(AppError::unprocessable(
+                    "`parent` is not a comment on this post",
+                ))
+            }
+        }
+    }
+
     let comment_id = Comment::insert(
         &state.pool,
         &Comment {
@@ -47,9 +246,55 @@ pub async fn create_comment(
             ..Default::default()
         },
     )
-    .await as _;
-    (
+    .await? as _;
+    Ok((
         StatusCode::CREATED,
         Json(CreateCommentResponse { comment_id }),
-    )
+    ))
+}
+
+/// Loads a comment of `post_id` that can still be edited or deleted.
+async fn find_live_comment(
+    state: &AppState,
+    post_id: i32,
+    comment_id: i32,
+) -> Result<Comment, AppError> {
+    match Comment::find_by_id(&state.pool, comment_id).await? {
+        Some(comment) if comment.post_id == post_id && !comment.is_deleted => Ok(comment),
+        _ => Err(AppError::not_found("Comment not found")),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCommentBody {
+    pub content: String,
+}
+
+pub async fn update_comment(
+    State(state): State<AppState>,
+    Path((post_id, comment_id)): Path<(i32, i32)>,
+    Auth(auth): Auth,
+    Json(body): Json<UpdateCommentBody>,
+) -> Result<Json<Comment>, AppError> {
+    let mut comment = find_live_comment(&state, post_id, comment_id).await?;
+    ensure_owner_or_manager(&state, &auth, comment.user_id).await?;
+
+    let edited_at = now();
+    comment.content = body.content;
+    comment.edited_at = Some(edited_at);
+    Comment::update_content(&state.pool, comment.comment_id, &comment.content, edited_at).await?;
+    Ok(Json(comment))
+}
+
+pub async fn delete_comment(
+    State(state): State<AppState>,
+    Path((post_id, comment_id)): Path<(i32, i32)>,
+    Auth(auth): Auth,
+) -> Result<StatusCode, AppError> {
+    let comment = find_live_comment(&state, post_id, comment_id).await?;
+    ensure_owner_or_manager(&state, &auth, comment.user_id).await?;
+
+    Comment::delete(&state.pool, &comment).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
@@ -0,0 +1,375 @@
+use std::{env, fmt, fs, io, net::SocketAddr, path::PathBuf, str::FromStr};
+
+use axum::http::HeaderValue;
+use jsonwebtoken::Algorithm;
+use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
+
+use crate::jwt::{JwtConfig, JwtKeyConfig};
+
+/// Prefix of every environment variable that overrides a config value.
+const ENV_PREFIX: &str = "SAGONGSA_";
+
+/// Used when neither `--config` nor `SAGONGSA_CONFIG` names a file. Missing is not an error.
+const DEFAULT_PATH: &str = "config.toml";
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub database_url: String,
+    pub pool_size: u32,
+    pub listen_addr: SocketAddr,
+    /// Origins allowed by CORS. `["*"]` allows any origin.
+    pub cors_origins: Vec<String>,
+    /// A `tracing_subscriber::EnvFilter` directive, e.g. `info,sagongsa_server=debug`.
+    pub log_filter: String,
+    pub upload: UploadConfig,
+    pub storage: StorageConfig,
+    pub jwt: JwtConfig,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct UploadConfig {
+    /// Largest accepted request body in bytes.
+    pub max_bytes: usize,
+    /// Largest accepted image file in bytes, e.g. an avatar. Must not exceed `max_bytes`.
+    pub max_image_bytes: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Local,
+    S3,
+}
+
+impl FromStr for StorageBackend {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<StorageBackend, ()> {
+        match value {
+            "local" => Ok(StorageBackend::Local),
+            "s3" => Ok(StorageBackend::S3),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    /// Directory uploads are written to with the `local` backend.
+    pub path: PathBuf,
+    pub s3: S3Config,
+    /// Seconds an uploaded file may stay unreferenced before cleanup deletes it. Covers the gap
+    /// between storing a file and saving the row that points at it.
+    pub orphan_grace: i64,
+    /// Seconds between orphan cleanup runs.
+    pub cleanup_interval: u64,
+}
+
+/// An S3-compatible bucket, addressed path-style (`{endpoint}/{bucket}/{key}`) so MinIO and
+/// similar servers work without wildcard DNS.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct S3Config {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            database_url: "sqlite:main.db".into(),
+            pool_size: 5,
+            listen_addr: ([0, 0, 0, 0], 4000).into(),
+            cors_origins: vec!["*".into()],
+            log_filter: "debug".into(),
+            upload: UploadConfig::default(),
+            storage: StorageConfig::default(),
+            jwt: JwtConfig::default(),
+        }
+    }
+}
+
+impl Default for UploadConfig {
+    fn default() -> UploadConfig {
+        UploadConfig {
+            max_bytes: 5 * 1024 * 1024,
+            max_image_bytes: 2 * 1024 * 1024,
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> StorageConfig {
+        StorageConfig {
+            backend: StorageBackend::Local,
+            path: "uploads".into(),
+            s3: S3Config::default(),
+            orphan_grace: 24 * 60 * 60,
+            cleanup_interval: 60 * 60,
+        }
+    }
+}
+
+impl Default for S3Config {
+    fn default() -> S3Config {
+        S3Config {
+            endpoint: String::new(),
+            bucket: String::new(),
+            region: "us-east-1".into(),
+            access_key_id: String::new(),
+            secret_access_key: String::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(String, io::Error),
+    Parse(String, toml::de::Error),
+    Env(String, String),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => write!(f, "cannot read {path}: {e}"),
+            ConfigError::Parse(path, e) => write!(f, "cannot parse {path}: {e}"),
+            ConfigError::Env(name, value) => write!(f, "{name} has an invalid value: {value:?}"),
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid configuration:")?;
+                for problem in problems {
+                    write!(f, "\n  - {problem}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Loads the file at `path`, falling back to `SAGONGSA_CONFIG` and then `config.toml`, and
+    /// applies `SAGONGSA_*` environment overrides.
+    pub fn load(path: Option<&str>) -> Result<Config, ConfigError> {
+        let explicit = path
+            .map(str::to_string)
+            .or_else(|| env::var(format!("{ENV_PREFIX}CONFIG")).ok());
+
+        let (path, text) = match explicit {
+            Some(path) => match fs::read_to_string(&path) {
+                Ok(text) => (path, text),
+                Err(e) => return Err(ConfigError::Read(path, e)),
+            },
+            None => match fs::read_to_string(DEFAULT_PATH) {
+                Ok(text) => (DEFAULT_PATH.to_string(), text),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    (DEFAULT_PATH.to_string(), String::new())
+                }
+                Err(e) => return Err(ConfigError::Read(DEFAULT_PATH.to_string(), e)),
+            },
+        };
+
+        Config::parse(&path, &text, |name| env::var(name).ok())
+    }
+
+    /// Parses `text` as TOML, then applies overrides looked up through `env` and validates the
+    /// result. `path` is only used in error messages.
+    pub fn parse(
+        path: &str,
+        text: &str,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Config, ConfigError> {
+        let mut config: Config =
+            toml::from_str(text).map_err(|e| ConfigError::Parse(path.to_string(), e))?;
+        config.apply_env(env)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn apply_env(&mut self, env: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        let var = |name: &str| {
+            let name = format!("{ENV_PREFIX}{name}");
+            env(&name).map(|value| (name, value))
+        };
+
+        if let Some((_, value)) = var("DATABASE_URL") {
+            self.database_url = value;
+        }
+        if let Some((name, value)) = var("POOL_SIZE") {
+            self.pool_size = parse_env(name, value)?;
+        }
+        if let Some((name, value)) = var("LISTEN_ADDR") {
+            self.listen_addr = parse_env(name, value)?;
+        }
+        if let Some((_, value)) = var("CORS_ORIGINS") {
+            self.cors_origins = value
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        if let Some((_, value)) = var("LOG_FILTER") {
+            self.log_filter = value;
+        }
+        if let Some((name, value)) = var("UPLOAD_MAX_BYTES") {
+            self.upload.max_bytes = parse_env(name, value)?;
+        }
+        if let Some((name, value)) = var("UPLOAD_MAX_IMAGE_BYTES") {
+            self.upload.max_image_bytes = parse_env(name, value)?;
+        }
+        if let Some((name, value)) = var("STORAGE_BACKEND") {
+            self.storage.backend = parse_env(name, value)?;
+        }
+        if let Some((_, value)) = var("STORAGE_PATH") {
+            self.storage.path = value.into();
+        }
+        if let Some((_, value)) = var("STORAGE_S3_ENDPOINT") {
+            self.storage.s3.endpoint = value;
+        }
+        if let Some((_, value)) = var("STORAGE_S3_BUCKET") {
+            self.storage.s3.bucket = value;
+        }
+        if let Some((_, value)) = var("STORAGE_S3_REGION") {
+            self.storage.s3.region = value;
+        }
+        if let Some((_, value)) = var("STORAGE_S3_ACCESS_KEY_ID") {
+            self.storage.s3.access_key_id = value;
+        }
+        if let Some((_, value)) = var("STORAGE_S3_SECRET_ACCESS_KEY") {
+            self.storage.s3.secret_access_key = value;
+        }
+        if let Some((_, value)) = var("JWT_ISSUER") {
+            self.jwt.issuer = value;
+        }
+        if let Some((_, value)) = var("JWT_AUDIENCE") {
+            self.jwt.audience = value;
+        }
+        if let Some((name, value)) = var("JWT_LIFETIME") {
+            self.jwt.lifetime = parse_env(name, value)?;
+        }
+        if let Some((name, value)) = var("JWT_REFRESH_LIFETIME") {
+            self.jwt.refresh_lifetime = parse_env(name, value)?;
+        }
+        // Replaces the active key with an HS256 key, which is the common single-secret setup.
+        // The unprefixed `JWT_SECRET` predates this file and is still accepted, but deprecated.
+        if let Some(secret) = var("JWT_SECRET")
+            .map(|(_, secret)| secret)
+            .or_else(|| env("JWT_SECRET"))
+        {
+            let kid = self.jwt.active_kid.clone();
+            self.jwt.keys.retain(|key| key.kid != kid);
+            self.jwt.keys.push(JwtKeyConfig {
+                kid,
+                algorithm: Algorithm::HS256,
+                secret: Some(secret),
+                private_key: None,
+                public_key: None,
+            });
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if !self.database_url.starts_with("sqlite:") {
+            problems.push(format!(
+                "database_url must be a sqlite: URL, got {:?}",
+                self.database_url
+            ));
+        }
+        if self.pool_size == 0 {
+            problems.push("pool_size must be at least 1".to_string());
+        }
+        if self.cors_origins.is_empty() {
+            problems.push("cors_origins must not be empty, use [\"*\"] to allow any".to_string());
+        }
+        for origin in &self.cors_origins {
+            let valid = origin == "*"
+                || ((origin.starts_with("http://") || origin.starts_with("https://"))
+                    && HeaderValue::from_str(origin).is_ok());
+            if !valid {
+                problems.push(format!("cors_origins: {origin:?} is not an http(s) origin"));
+            }
+        }
+        if let Err(e) = EnvFilter::try_new(&self.log_filter) {
+            problems.push(format!("log_filter {:?}: {e}", self.log_filter));
+        }
+        if self.upload.max_bytes == 0 {
+            problems.push("upload.max_bytes must be positive".to_string());
+        }
+        if self.upload.max_image_bytes == 0 || self.upload.max_image_bytes > self.upload.max_bytes {
+            problems.push(
+                "upload.max_image_bytes must be positive and at most upload.max_bytes".to_string(),
+            );
+        }
+        match self.storage.backend {
+            StorageBackend::Local => {
+                if self.storage.path.as_os_str().is_empty() {
+                    problems.push("storage.path must not be empty".to_string());
+                }
+            }
+            StorageBackend::S3 => {
+                let s3 = &self.storage.s3;
+                if !(s3.endpoint.starts_with("http://") || s3.endpoint.starts_with("https://")) {
+                    problems.push(format!(
+                        "storage.s3.endpoint must be an http(s) URL, got {:?}",
+                        s3.endpoint
+                    ));
+                }
+                if s3.bucket.is_empty() || s3.region.is_empty() {
+                    problems
+                        .push("storage.s3.bucket and storage.s3.region are required".to_string());
+                }
+                if s3.access_key_id.is_empty() || s3.secret_access_key.is_empty() {
+                    problems.push(
+                        "storage.s3.access_key_id and storage.s3.secret_access_key are required"
+                            .to_string(),
+                    );
+                }
+            }
+        }
+        if self.storage.orphan_grace < 0 || self.storage.cleanup_interval == 0 {
+            problems.push(
+                "storage.orphan_grace must not be negative and storage.cleanup_interval must be positive"
+                    .to_string(),
+            );
+        }
+        if self.jwt.lifetime <= 0 || self.jwt.refresh_lifetime <= 0 {
+            problems.push("jwt.lifetime and jwt.refresh_lifetime must be positive".to_string());
+        }
+        if !self
+            .jwt
+            .keys
+            .iter()
+            .any(|key| key.kid == self.jwt.active_kid)
+        {
+            problems.push(format!(
+                "jwt.active_kid {:?} has no matching jwt.keys entry (or set {ENV_PREFIX}JWT_SECRET)",
+                self.jwt.active_kid
+            ));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+}
+
+fn parse_env<T: FromStr>(name: String, value: String) -> Result<T, ConfigError> {
+    value.parse().map_err(|_| ConfigError::Env(name, value))
+}
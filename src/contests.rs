@@ -1,33 +1,289 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
-    Json,
+    extract::{FromRequest, Request, State},
+    http::{header::CONTENT_TYPE, StatusCode},
 };
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
 
 use crate::{
-    auth::Auth,
-    models::{Contest, Post},
+    auth::{ensure_owner_or_manager, Auth, MaybeAuth, RequireManager},
+    error::AppError,
+    extract::{Json, Multipart, Path, Query},
+    files::{self, file_url},
+    images,
+    jwt::Claims,
+    models::{
+        Bookmark, Contest, ContestFilter, ContestSort, ContestStatus, ItemKind, Like, Post,
+        PosterRatio,
+    },
+    pagination::{into_page, page_limit, Cursor, Page},
+    posts::{post_responses, PostResponse},
+    utils::now,
     AppState,
 };
 
-pub async fn list_contests(State(state): State<AppState>) -> impl IntoResponse {
-    let contests = Contest::find_all(&state.pool).await;
-    Json(contests)
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListContestsQuery {
+    field: Option<i32>,
+    status: Option<ContestStatus>,
+    prize: Option<String>,
+    title: Option<String>,
+    #[serde(default)]
+    sort: ContestSort,
+    cursor: Option<String>,
+    limit: Option<i64>,
+    #[serde(default)]
+    include_total: bool,
+}
+
+/// URLs of an uploaded poster in each stored size.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PosterUrls {
+    thumbnail: String,
+    card: String,
+    full: String,
+}
+
+impl PosterUrls {
+    fn of(contest: &Contest) -> Option<PosterUrls> {
+        let full = contest.poster_key.as_ref()?;
+        // Posters uploaded before they were stored in several sizes only have the full one.
+        Some(PosterUrls {
+            thumbnail: file_url(contest.poster_thumbnail_key.as_ref().unwrap_or(full)),
+            card: file_url(contest.poster_card_key.as_ref().unwrap_or(full)),
+            full: file_url(full),
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContestResponse {
+    #[serde(flatten)]
+    contest: Contest,
+    /// `None` unless a poster was uploaded; `img` may still hold an external URL.
+    poster: Option<PosterUrls>,
+    /// Only present when the request is authenticated, like `bookmarked_by_me`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    liked_by_me: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bookmarked_by_me: Option<bool>,
+}
+
+/// Adds what `viewer` did with each contest, if anyone is signed in.
+pub async fn contest_responses(
+    state: &AppState,
+    viewer: Option<&Claims>,
+    contests: Vec<Contest>,
+) -> Result<Vec<ContestResponse>, AppError> {
+    let (liked, bookmarked) = match viewer {
+        Some(claims) => {
+            let ids = contests
+                .iter()
+                .map(|contest| contest.contest_id)
+                .collect::<Vec<_>>();
+            (
+                Some(Like::find_liked(&state.pool, claims.sub, ItemKind::Contest, &ids).await?),
+                Some(
+                    Bookmark::find_bookmarked(&state.pool, claims.sub, ItemKind::Contest, &ids)
+                        .await?,
+                ),
+            )
+        }
+        None => (None, None),
+    };
+    Ok(contests
+        .into_iter()
+        .map(|contest| ContestResponse {
+            poster: PosterUrls::of(&contest),
+            liked_by_me: liked
+                .as_ref()
+                .map(|liked| liked.contains(&contest.contest_id)),
+            bookmarked_by_me: bookmarked
+                .as_ref()
+                .map(|bookmarked| bookmarked.contains(&contest.contest_id)),
+            contest,
+        })
+        .collect())
+}
+
+pub async fn list_contests(
+    State(state): State<AppState>,
+    MaybeAuth(viewer): MaybeAuth,
+    Query(query): Query<ListContestsQuery>,
+) -> Result<Json<Page<ContestResponse>>, AppError> {
+    let filter = ContestFilter {
+        field: query.field,
+        status: query.status,
+        prize: query.prize.filter(|prize| !prize.is_empty()),
+        title: query.title.filter(|title| !title.is_empty()),
+        now: now(),
+    };
+    let sort = query.sort;
+    let after = query
+        .cursor
+        .map(|cursor| Cursor::decode(&cursor, sort.name()))
+        .transpose()?;
+    let limit = page_limit(query.limit);
+
+    let contests = Contest::search(&state.pool, &filter, sort, after, limit + 1).await?;
+    let total = if query.include_total {
+        Some(Contest::count(&state.pool, &filter).await?)
+    } else {
+        None
+    };
+    let page = into_page(
+        contests,
+        limit,
+        sort.name(),
+        |contest| sort.cursor(contest),
+        total,
+    );
+    Ok(Json(Page {
+        items: contest_responses(&state, viewer.as_ref(), page.items).await?,
+        next_cursor: page.next_cursor,
+        total: page.total,
+    }))
 }
 
 pub async fn get_contest(
     State(state): State<AppState>,
+    MaybeAuth(viewer): MaybeAuth,
     Path(contest_id): Path<i32>,
-) -> impl IntoResponse {
-    let contest = Contest::find_by_id(&state.pool, contest_id).await;
-    match contest {
-        Some(contest) => Json(contest).into_response(),
-        None => (StatusCode::NOT_FOUND, Json("Not Found")).into_response(),
+) -> Result<Json<ContestResponse>, AppError> {
+    let Some(contest) = Contest::find_by_id(&state.pool, contest_id).await? else {
+        return Err(AppError::not_found("Contest not found"));
+    };
+    let mut contests = contest_responses(&state, viewer.as_ref(), vec![contest]).await?;
+    Ok(Json(contests.remove(0)))
+}
+
+/// Widths of the stored poster sizes, in pixels. Heights follow from the contest's ratio.
+const POSTER_FULL_WIDTH: u32 = 1200;
+const POSTER_CARD_WIDTH: u32 = 480;
+const POSTER_THUMBNAIL_WIDTH: u32 = 240;
+
+/// Body of contest create and update: JSON, or `multipart/form-data` with the same JSON in a
+/// `contest` field and a poster image in a `poster` field.
+pub struct ContestForm<T> {
+    body: T,
+    poster: Option<Vec<u8>>,
+}
+
+#[async_trait::async_trait]
+impl<T: DeserializeOwned + Send> FromRequest<AppState> for ContestForm<T> {
+    type Rejection = AppError;
+
+    async fn from_request(request: Request, state: &AppState) -> Result<Self, Self::Rejection> {
+        let is_multipart = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("multipart/form-data"));
+        if !is_multipart {
+            let Json(body) = Json::from_request(request, state).await?;
+            return Ok(ContestForm { body, poster: None });
+        }
+
+        let Multipart(mut multipart) = Multipart::from_request(request, state).await?;
+        let (mut body, mut poster) = (None, None);
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|e| AppError::unprocessable(e.body_text()))?
+        {
+            match field.name() {
+                Some("contest") => {
+                    let text = field
+                        .text()
+                        .await
+                        .map_err(|e| AppError::unprocessable(e.body_text()))?;
+                    body = Some(serde_json::from_str(&text).map_err(|e| {
+                        AppError::unprocessable(format!("Invalid `contest` field: {e}"))
+                    })?);
+                }
+                Some("poster") => {
+                    poster =
+                        Some(files::read_field(field, state.config.upload.max_image_bytes).await?);
+                }
+                _ => {}
+            }
+        }
+        match body {
+            Some(body) => Ok(ContestForm { body, poster }),
+            None => Err(AppError::unprocessable("Missing `contest` field")),
+        }
     }
 }
 
+/// Checks `bytes` against `ratio`, stores the poster in every size and points `contest` at it.
+async fn store_poster(
+    state: &AppState,
+    contest: &mut Contest,
+    bytes: Vec<u8>,
+    ratio: PosterRatio,
+) -> Result<(), AppError> {
+    let encoded = tokio::task::spawn_blocking(move || {
+        let image = images::decode(&bytes)?;
+        if image.width() < POSTER_THUMBNAIL_WIDTH {
+            return Err(AppError::unprocessable(format!(
+                "Posters must be at least {POSTER_THUMBNAIL_WIDTH} pixels wide"
+            )));
+        }
+        if !images::has_ratio(&image, ratio.parts()) {
+            return Err(AppError::Unprocessable(
+                "Poster does not match `ratio`".into(),
+                Some(json!({
+                    "ratio": ratio,
+                    "width": image.width(),
+                    "height": image.height(),
+                })),
+            ));
+        }
+        // Re-encoding also drops metadata such as EXIF locations.
+        [POSTER_FULL_WIDTH, POSTER_CARD_WIDTH, POSTER_THUMBNAIL_WIDTH]
+            .into_iter()
+            .map(|width| images::encode(&images::fit(&image, ratio.parts(), width)))
+            .collect::<Result<Vec<_>, AppError>>()
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("poster task failed: {e}")))??;
+
+    let mut keys = Vec::new();
+    for (data, content_type) in encoded {
+        keys.push(state.files.store(data, content_type).await?);
+    }
+    let [full, card, thumbnail] = <[String; 3]>::try_from(keys).expect("three poster sizes");
+    contest.img = Some(file_url(&full));
+    contest.ratio = Some(ratio);
+    contest.poster_key = Some(full);
+    contest.poster_card_key = Some(card);
+    contest.poster_thumbnail_key = Some(thumbnail);
+    Ok(())
+}
+
+/// For contests from before ratios were restricted, which may not have one.
+fn missing_ratio() -> AppError {
+    AppError::unprocessable("Set the contest's `ratio` before uploading a poster")
+}
+
+fn clear_poster(contest: &mut Contest) {
+    contest.poster_key = None;
+    contest.poster_card_key = None;
+    contest.poster_thumbnail_key = None;
+}
+
+fn check_dates(contest: &Contest) -> Result<(), AppError> {
+    if contest.started_at > contest.ended_at {
+        return Err(AppError::unprocessable(
+            "`startedAt` must not be after `endedAt`",
+        ));
+    }
+    Ok(())
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateContestBody {
@@ -38,58 +294,194 @@ pub struct CreateContestBody {
     prize: String,
     link: String,
     img: Option<String>,
-    ratio: String,
+    ratio: PosterRatio,
 }
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-struct CreateContestResponse {
+pub struct CreateContestResponse {
     contest_id: i32,
 }
 
 pub async fn create_contest(
     State(state): State<AppState>,
-    Auth(auth): Auth,
-    Json(body): Json<CreateContestBody>,
-) -> impl IntoResponse {
-    let contest_id = Contest::insert(
-        &state.pool,
-        &Contest {
-            user_id: auth.sub,
-            title: body.title,
-            prize: body.prize,
-            started_at: body.started_at,
-            ended_at: body.ended_at,
-            link: body.link,
-            field: body.field,
-            img: body.img,
-            ratio: body.ratio,
-            ..Contest::default()
-        },
-    )
-    .await as _;
-
-    (
+    RequireManager(auth): RequireManager,
+    ContestForm { body, poster }: ContestForm<CreateContestBody>,
+) -> Result<(StatusCode, Json<CreateContestResponse>), AppError> {
+    let mut contest = Contest {
+        user_id: auth.sub,
+        title: body.title,
+        prize: body.prize,
+        started_at: body.started_at,
+        ended_at: body.ended_at,
+        link: body.link,
+        field: body.field,
+        img: body.img,
+        ratio: Some(body.ratio),
+        ..Contest::default()
+    };
+    check_dates(&contest)?;
+    if let Some(poster) = poster {
+        store_poster(&state, &mut contest, poster, body.ratio).await?;
+    }
+    let contest_id = Contest::insert(&state.pool, &contest).await? as _;
+
+    Ok((
         StatusCode::CREATED,
         Json(CreateContestResponse { contest_id }),
-    )
+    ))
 }
 
-pub async fn delete_contests(State(state): State<AppState>) {
-    sqlx::query("DELETE FROM posts WHERE contest_id IS NOT NULL")
-        .execute(&state.pool)
-        .await
-        .unwrap();
-    sqlx::query("DELETE FROM contests")
-        .execute(&state.pool)
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateContestBody {
+    title: Option<String>,
+    field: Option<i32>,
+    started_at: Option<i64>,
+    ended_at: Option<i64>,
+    prize: Option<String>,
+    link: Option<String>,
+    img: Option<String>,
+    ratio: Option<PosterRatio>,
+}
+
+pub async fn update_contest(
+    State(state): State<AppState>,
+    Path(contest_id): Path<i32>,
+    Auth(auth): Auth,
+    ContestForm { body, poster }: ContestForm<UpdateContestBody>,
+) -> Result<Json<ContestResponse>, AppError> {
+    let Some(mut contest) = Contest::find_by_id(&state.pool, contest_id).await? else {
+        return Err(AppError::not_found("Contest not found"));
+    };
+    ensure_owner_or_manager(&state, &auth, contest.user_id).await?;
+
+    if let Some(title) = body.title {
+        contest.title = title;
+    }
+    if let Some(field) = body.field {
+        contest.field = field;
+    }
+    if let Some(started_at) = body.started_at {
+        contest.started_at = started_at;
+    }
+    if let Some(ended_at) = body.ended_at {
+        contest.ended_at = ended_at;
+    }
+    if let Some(prize) = body.prize {
+        contest.prize = prize;
+    }
+    if let Some(link) = body.link {
+        contest.link = link;
+    }
+    if let Some(img) = body.img {
+        // An explicit URL replaces an uploaded poster.
+        contest.img = Some(img);
+        clear_poster(&mut contest);
+    }
+    let ratio = body.ratio.or(contest.ratio);
+
+    check_dates(&contest)?;
+    match (poster, ratio) {
+        (Some(poster), Some(ratio)) => store_poster(&state, &mut contest, poster, ratio).await?,
+        (Some(_), None) => return Err(missing_ratio()),
+        (None, _) if ratio != contest.ratio && contest.poster_key.is_some() => {
+            return Err(AppError::unprocessable(
+                "Changing `ratio` needs a new poster in that ratio",
+            ))
+        }
+        (None, _) => contest.ratio = ratio,
+    }
+
+    Contest::update(&state.pool, &contest).await?;
+    let mut contests = contest_responses(&state, Some(&auth), vec![contest]).await?;
+    Ok(Json(contests.remove(0)))
+}
+
+/// Replaces the contest's poster with the image in the `poster` field of a multipart body, which
+/// must match the contest's ratio.
+pub async fn upload_poster(
+    State(state): State<AppState>,
+    Path(contest_id): Path<i32>,
+    Auth(auth): Auth,
+    Multipart(mut multipart): Multipart,
+) -> Result<Json<ContestResponse>, AppError> {
+    let Some(mut contest) = Contest::find_by_id(&state.pool, contest_id).await? else {
+        return Err(AppError::not_found("Contest not found"));
+    };
+    ensure_owner_or_manager(&state, &auth, contest.user_id).await?;
+
+    let mut bytes = None;
+    while let Some(field) = multipart
+        .next_field()
         .await
-        .unwrap();
+        .map_err(|e| AppError::unprocessable(e.body_text()))?
+    {
+        if field.name() == Some("poster") {
+            bytes = Some(files::read_field(field, state.config.upload.max_image_bytes).await?);
+            break;
+        }
+    }
+    let Some(bytes) = bytes else {
+        return Err(AppError::unprocessable("Missing `poster` file field"));
+    };
+
+    let Some(ratio) = contest.ratio else {
+        return Err(missing_ratio());
+    };
+    store_poster(&state, &mut contest, bytes, ratio).await?;
+    Contest::update(&state.pool, &contest).await?;
+    let mut contests = contest_responses(&state, Some(&auth), vec![contest]).await?;
+    Ok(Json(contests.remove(0)))
+}
+
+pub async fn delete_poster(
+    State(state): State<AppState>,
+    Path(contest_id): Path<i32>,
+    Auth(auth): Auth,
+) -> Result<StatusCode, AppError> {
+    let Some(mut contest) = Contest::find_by_id(&state.pool, contest_id).await? else {
+        return Err(AppError::not_found("Contest not found"));
+    };
+    ensure_owner_or_manager(&state, &auth, contest.user_id).await?;
+
+    if contest.poster_key.is_some() {
+        contest.img = None;
+        clear_poster(&mut contest);
+        Contest::update(&state.pool, &contest).await?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn delete_contest(
+    State(state): State<AppState>,
+    Path(contest_id): Path<i32>,
+    Auth(auth): Auth,
+) -> Result<StatusCode, AppError> {
+    let Some(contest) = Contest::find_by_id(&state.pool, contest_id).await? else {
+        return Err(AppError::not_found("Contest not found"));
+    };
+    ensure_owner_or_manager(&state, &auth, contest.user_id).await?;
+
+    Contest::delete(&state.pool, contest.contest_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Deletes every contest. Linked posts and teams are detached rather than deleted, as in
+/// [`Contest::delete`].
+pub async fn delete_contests(
+    State(state): State<AppState>,
+    _: RequireManager,
+) -> Result<StatusCode, AppError> {
+    Contest::delete_all(&state.pool).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn list_linked_posts(
     State(state): State<AppState>,
+    MaybeAuth(viewer): MaybeAuth,
     Path(contest_id): Path<i32>,
-) -> impl IntoResponse {
-    let posts = Post::find_by_contest_id(&state.pool, contest_id).await;
-    Json(posts).into_response()
+) -> Result<Json<Vec<PostResponse>>, AppError> {
+    let posts = Post::find_by_contest_id(&state.pool, contest_id).await?;
+    Ok(Json(post_responses(&state, viewer.as_ref(), posts).await?))
 }
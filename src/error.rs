@@ -0,0 +1,196 @@
+use axum::{
+    extract::{
+        multipart::MultipartRejection,
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::error::ErrorKind;
+
+use crate::storage::StorageError;
+
+/// Error returned by handlers. Every variant is rendered as
+/// `{ "code": ..., "message": ..., "details": ... }` with a matching status code.
+#[derive(Debug)]
+pub enum AppError {
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String, Option<Value>),
+    PayloadTooLarge(String),
+    UnsupportedMediaType(String),
+    Unprocessable(String, Option<Value>),
+    Internal(String),
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    code: &'static str,
+    message: String,
+    details: Option<Value>,
+}
+
+impl AppError {
+    pub fn unauthorized(message: impl Into<String>) -> AppError {
+        AppError::Unauthorized(message.into())
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> AppError {
+        AppError::Forbidden(message.into())
+    }
+
+    pub fn not_found(message: impl Into<String>) -> AppError {
+        AppError::NotFound(message.into())
+    }
+
+    pub fn conflict(message: impl Into<String>) -> AppError {
+        AppError::Conflict(message.into(), None)
+    }
+
+    pub fn unprocessable(message: impl Into<String>) -> AppError {
+        AppError::Unprocessable(message.into(), None)
+    }
+
+    /// Keeps the status axum picked for a rejected request, but renders it like any other error.
+    fn rejected(status: StatusCode, message: String) -> AppError {
+        match status {
+            StatusCode::PAYLOAD_TOO_LARGE => AppError::PayloadTooLarge(message),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => AppError::UnsupportedMediaType(message),
+            StatusCode::UNPROCESSABLE_ENTITY => AppError::Unprocessable(message, None),
+            status if status.is_server_error() => AppError::Internal(message),
+            _ => AppError::BadRequest(message),
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, code, message, details) = match self {
+            AppError::BadRequest(message) => {
+                (StatusCode::BAD_REQUEST, "bad_request", message, None)
+            }
+            AppError::Unauthorized(message) => {
+                (StatusCode::UNAUTHORIZED, "unauthorized", message, None)
+            }
+            AppError::Forbidden(message) => (StatusCode::FORBIDDEN, "forbidden", message, None),
+            AppError::NotFound(message) => (StatusCode::NOT_FOUND, "not_found", message, None),
+            AppError::Conflict(message, details) => {
+                (StatusCode::CONFLICT, "conflict", message, details)
+            }
+            AppError::PayloadTooLarge(message) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "payload_too_large",
+                message,
+                None,
+            ),
+            AppError::UnsupportedMediaType(message) => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "unsupported_media_type",
+                message,
+                None,
+            ),
+            AppError::Unprocessable(message, details) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "unprocessable",
+                message,
+                details,
+            ),
+            AppError::Internal(message) => {
+                tracing::error!("{message}");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "internal",
+                    "Internal server error".to_string(),
+                    None,
+                )
+            }
+        };
+
+        (
+            status,
+            Json(ErrorBody {
+                code,
+                message,
+                details,
+            }),
+        )
+            .into_response()
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(error: sqlx::Error) -> AppError {
+        if let sqlx::Error::Database(db_error) = &error {
+            let details = Some(json!({ "constraint": db_error.message() }));
+            match db_error.kind() {
+                ErrorKind::UniqueViolation => {
+                    return AppError::Conflict("Resource already exists".into(), details)
+                }
+                ErrorKind::ForeignKeyViolation => {
+                    return AppError::Unprocessable(
+                        "Referenced resource does not exist".into(),
+                        details,
+                    )
+                }
+                ErrorKind::NotNullViolation | ErrorKind::CheckViolation => {
+                    return AppError::Unprocessable("Invalid value".into(), details)
+                }
+                _ => {}
+            }
+        }
+        AppError::Internal(format!("database error: {error}"))
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> AppError {
+        AppError::rejected(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(rejection: PathRejection) -> AppError {
+        AppError::rejected(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> AppError {
+        AppError::rejected(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<MultipartRejection> for AppError {
+    fn from(rejection: MultipartRejection) -> AppError {
+        AppError::rejected(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for AppError {
+    fn from(error: jsonwebtoken::errors::Error) -> AppError {
+        AppError::Internal(format!("jwt error: {error}"))
+    }
+}
+
+impl From<argon2::password_hash::Error> for AppError {
+    fn from(error: argon2::password_hash::Error) -> AppError {
+        AppError::Internal(format!("password hash error: {error}"))
+    }
+}
+
+impl From<image::ImageError> for AppError {
+    fn from(error: image::ImageError) -> AppError {
+        AppError::Internal(format!("image error: {error}"))
+    }
+}
+
+impl From<StorageError> for AppError {
+    fn from(error: StorageError) -> AppError {
+        AppError::Internal(format!("storage error: {error}"))
+    }
+}
@@ -0,0 +1,72 @@
+use axum::{
+    extract::{FromRequest, FromRequestParts, Request},
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::AppError;
+
+// axum's own extractors reject with plain text. These wrap them so a malformed body, path or
+// query string gets the same JSON error body as everything else.
+
+/// [`axum::Json`] rejecting with [`AppError`]. Also usable as a response.
+pub struct Json<T>(pub T);
+
+#[async_trait::async_trait]
+impl<T: DeserializeOwned, S: Send + Sync> FromRequest<S> for Json<T> {
+    type Rejection = AppError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::Json(value) = axum::Json::from_request(request, state).await?;
+        Ok(Json(value))
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+/// [`axum::extract::Path`] rejecting with [`AppError`].
+pub struct Path<T>(pub T);
+
+#[async_trait::async_trait]
+impl<T: DeserializeOwned + Send, S: Send + Sync> FromRequestParts<S> for Path<T> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Path(value) =
+            axum::extract::Path::from_request_parts(parts, state).await?;
+        Ok(Path(value))
+    }
+}
+
+/// [`axum::extract::Query`] rejecting with [`AppError`].
+pub struct Query<T>(pub T);
+
+#[async_trait::async_trait]
+impl<T: DeserializeOwned, S: Send + Sync> FromRequestParts<S> for Query<T> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Query(value) =
+            axum::extract::Query::from_request_parts(parts, state).await?;
+        Ok(Query(value))
+    }
+}
+
+/// [`axum::extract::Multipart`] rejecting with [`AppError`].
+pub struct Multipart(pub axum::extract::Multipart);
+
+#[async_trait::async_trait]
+impl<S: Send + Sync> FromRequest<S> for Multipart {
+    type Rejection = AppError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Multipart(
+            axum::extract::Multipart::from_request(request, state).await?,
+        ))
+    }
+}
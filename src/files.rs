@@ -0,0 +1,177 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{multipart::Field, State},
+    http::header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE, X_CONTENT_TYPE_OPTIONS},
+    response::IntoResponse,
+};
+use sqlx::SqlitePool;
+use tokio::sync::{Notify, RwLock};
+
+use crate::{
+    error::AppError,
+    extract::Path,
+    models::StoredFile,
+    storage::Storage,
+    utils::{now, sha256_hex},
+    AppState,
+};
+
+/// Content types served inline. Anything else, SVG included, is sent as a download so an
+/// uploaded file can never run script on this origin.
+const INLINE_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// Uploaded files: the storage backend plus the `files` rows that track what it holds.
+pub struct Files {
+    pool: SqlitePool,
+    storage: Arc<dyn Storage>,
+    /// Held shared while storing and exclusively while cleanup deletes an orphan's row and
+    /// marks it in `deleting`, so the two cannot interleave.
+    gate: RwLock<()>,
+    /// Orphans whose row is gone but whose content is still being deleted from storage. Storing
+    /// the same content again waits for `deleted` instead of racing that delete.
+    deleting: Mutex<HashSet<String>>,
+    deleted: Notify,
+}
+
+/// Public URL of the file stored under `key`.
+pub fn file_url(key: &str) -> String {
+    format!("/files/{key}")
+}
+
+impl Files {
+    pub fn new(pool: SqlitePool, storage: Arc<dyn Storage>) -> Files {
+        Files {
+            pool,
+            storage,
+            gate: RwLock::new(()),
+            deleting: Mutex::default(),
+            deleted: Notify::new(),
+        }
+    }
+
+    /// Stores `data` and returns its key, which is derived from the content so identical uploads
+    /// share one file. The caller must reference the key within `storage.orphan_grace`.
+    pub async fn store(&self, data: Vec<u8>, content_type: &str) -> Result<String, AppError> {
+        let key = format!("{}{}", sha256_hex(&data), extension(content_type));
+        let file = StoredFile {
+            key: key.clone(),
+            content_type: content_type.to_string(),
+            size: data.len() as i64,
+            created_at: now(),
+        };
+
+        loop {
+            // Created first so a delete finishing after the check still wakes us.
+            let deleted = self.deleted.notified();
+            {
+                let _gate = self.gate.read().await;
+                if !self.deleting.lock().unwrap().contains(&key) {
+                    StoredFile::upsert(&self.pool, &file).await?;
+                    self.storage.put(&key, content_type, data).await?;
+                    return Ok(key);
+                }
+            }
+            deleted.await;
+        }
+    }
+
+    pub async fn load(&self, key: &str) -> Result<Option<Vec<u8>>, AppError> {
+        Ok(self.storage.get(key).await?)
+    }
+
+    /// Deletes every file that nothing has referenced for `grace` seconds and returns how many
+    /// there were.
+    pub async fn clean_up(&self, grace: i64) -> Result<usize, AppError> {
+        let before = now() - grace;
+        let mut deleted = 0;
+        for key in StoredFile::find_orphans(&self.pool, before).await? {
+            {
+                let _gate = self.gate.write().await;
+                // Checked again: the file may have been stored again or referenced meanwhile.
+                if !StoredFile::delete_orphan(&self.pool, &key, before).await? {
+                    continue;
+                }
+                self.deleting.lock().unwrap().insert(key.clone());
+            }
+            // Outside the gate, so uploads of other content do not wait for the backend.
+            let result = self.storage.delete(&key).await;
+            self.deleting.lock().unwrap().remove(&key);
+            self.deleted.notify_waiters();
+            result?;
+            deleted += 1;
+        }
+        Ok(deleted)
+    }
+}
+
+/// File extension for keys, so files in a bucket or directory are recognizable.
+fn extension(content_type: &str) -> &'static str {
+    match content_type {
+        "image/png" => ".png",
+        "image/jpeg" => ".jpg",
+        "image/gif" => ".gif",
+        "image/webp" => ".webp",
+        "application/pdf" => ".pdf",
+        "text/plain" => ".txt",
+        "application/zip" => ".zip",
+        _ => "",
+    }
+}
+
+/// Reads an uploaded file from a multipart field, failing with 413 as soon as it grows past
+/// `max_bytes` instead of buffering all of it first.
+pub async fn read_field(mut field: Field<'_>, max_bytes: usize) -> Result<Vec<u8>, AppError> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| AppError::unprocessable(e.body_text()))?
+    {
+        if bytes.len() + chunk.len() > max_bytes {
+            return Err(AppError::PayloadTooLarge(format!(
+                "Files may be at most {max_bytes} bytes"
+            )));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+pub async fn get_file(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    // Only keys this server handed out reach the backend.
+    let Some(file) = StoredFile::find(&state.pool, &key).await? else {
+        return Err(AppError::not_found("File not found"));
+    };
+    let Some(data) = state.files.load(&file.key).await? else {
+        return Err(AppError::Internal(format!(
+            "file {} is missing from storage",
+            file.key
+        )));
+    };
+
+    let disposition = if INLINE_TYPES.contains(&file.content_type.as_str()) {
+        "inline"
+    } else {
+        "attachment"
+    };
+    Ok((
+        [
+            (CONTENT_TYPE, file.content_type),
+            (CONTENT_DISPOSITION, disposition.to_string()),
+            (X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            // The key changes whenever the content does.
+            (
+                CACHE_CONTROL,
+                "public, max-age=31536000, immutable".to_string(),
+            ),
+        ],
+        data,
+    ))
+}
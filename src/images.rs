@@ -0,0 +1,93 @@
+use std::io::Cursor;
+
+use image::{
+    codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ImageFormat, ImageReader, Limits,
+};
+
+use crate::error::AppError;
+
+/// Largest width or height accepted on upload, so a small file cannot decode into a huge
+/// bitmap.
+const MAX_DIMENSION: u32 = 8192;
+
+const JPEG_QUALITY: u8 = 85;
+
+/// How far, relative to the declared ratio, an image's aspect ratio may be off and still count
+/// as matching. The difference is cropped away.
+const RATIO_TOLERANCE: f64 = 0.02;
+
+/// Decodes an uploaded PNG, JPEG, GIF or WebP image. The format is sniffed from the file's
+/// leading bytes; whatever content type the client declared is ignored.
+pub fn decode(bytes: &[u8]) -> Result<DynamicImage, AppError> {
+    let format = match image::guess_format(bytes) {
+        Ok(
+            format @ (ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP),
+        ) => format,
+        _ => {
+            return Err(AppError::unprocessable(
+                "Only PNG, JPEG, GIF and WebP images are accepted",
+            ))
+        }
+    };
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    reader.limits(limits);
+    reader
+        .decode()
+        .map_err(|e| AppError::unprocessable(format!("Cannot decode image: {e}")))
+}
+
+/// Crops the centre square out of `image` and scales it to `size` by `size` pixels.
+pub fn square(image: &DynamicImage, size: u32) -> DynamicImage {
+    let side = image.width().min(image.height());
+    let x = (image.width() - side) / 2;
+    let y = (image.height() - side) / 2;
+    image
+        .crop_imm(x, y, side, side)
+        .resize_exact(size, size, FilterType::Lanczos3)
+}
+
+/// Whether `image` has a `width:height` aspect ratio, give or take [`RATIO_TOLERANCE`].
+pub fn has_ratio(image: &DynamicImage, (width, height): (u32, u32)) -> bool {
+    let actual = image.width() as f64 / image.height() as f64;
+    let expected = width as f64 / height as f64;
+    (actual / expected - 1.0).abs() <= RATIO_TOLERANCE
+}
+
+/// Crops the centre of `image` to exactly `width:height` and scales it down to `max_width`
+/// pixels wide. Images narrower than that are cropped but not enlarged.
+pub fn fit(image: &DynamicImage, (width, height): (u32, u32), max_width: u32) -> DynamicImage {
+    let unit = (image.width() / width).min(image.height() / height).max(1);
+    let (crop_width, crop_height) = (
+        (unit * width).min(image.width()),
+        (unit * height).min(image.height()),
+    );
+    let cropped = image.crop_imm(
+        (image.width() - crop_width) / 2,
+        (image.height() - crop_height) / 2,
+        crop_width,
+        crop_height,
+    );
+    if max_width >= crop_width {
+        return cropped;
+    }
+    cropped.resize_exact(max_width, max_width * height / width, FilterType::Lanczos3)
+}
+
+/// Encodes `image` as PNG if it has an alpha channel and as JPEG otherwise. Returns the bytes
+/// and their content type.
+pub fn encode(image: &DynamicImage) -> Result<(Vec<u8>, &'static str), AppError> {
+    let mut out = Cursor::new(Vec::new());
+    let content_type = if image.color().has_alpha() {
+        image.write_to(&mut out, ImageFormat::Png)?;
+        "image/png"
+    } else {
+        let encoder = JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY);
+        image.to_rgb8().write_with_encoder(encoder)?;
+        "image/jpeg"
+    };
+    Ok((out.into_inner(), content_type))
+}
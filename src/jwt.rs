@@ -0,0 +1,319 @@
+use std::{collections::HashMap, fs};
+
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::now;
+
+/// The `[jwt]` section of [`Config`](crate::config::Config).
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct JwtConfig {
+    pub issuer: String,
+    pub audience: String,
+    /// Access token lifetime in seconds.
+    pub lifetime: i64,
+    /// Refresh token lifetime in seconds.
+    pub refresh_lifetime: i64,
+    /// `kid` of the key used to sign new tokens. Every other key is only used for verification.
+    pub active_kid: String,
+    pub keys: Vec<JwtKeyConfig>,
+}
+
+impl Default for JwtConfig {
+    fn default() -> JwtConfig {
+        JwtConfig {
+            issuer: "sagongsa".into(),
+            audience: "sagongsa".into(),
+            lifetime: 15 * 60,
+            refresh_lifetime: 30 * 24 * 60 * 60,
+            active_kid: "default".into(),
+            keys: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct JwtKeyConfig {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    /// Shared secret for HS256.
+    pub secret: Option<String>,
+    /// Path to a PEM private key for RS256 and EdDSA. Only needed for the active key.
+    pub private_key: Option<String>,
+    /// Path to a PEM public key for RS256 and EdDSA.
+    pub public_key: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: i32, // Subject (whom the token refers to)
+    pub sid: i32, // Session the token was issued for
+    pub exp: i64,
+    pub iat: i64,
+    pub iss: String,
+    pub aud: String,
+}
+
+/// Signing and verification keys built from a [`JwtConfig`].
+pub struct JwtKeys {
+    issuer: String,
+    audience: String,
+    lifetime: i64,
+    refresh_lifetime: i64,
+    active_kid: String,
+    active_algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_keys: HashMap<String, (Algorithm, DecodingKey)>,
+}
+
+impl JwtKeys {
+    pub fn from_config(config: &JwtConfig) -> Result<JwtKeys, String> {
+        let mut encoding_key = None;
+        let mut decoding_keys = HashMap::new();
+
+        for key in &config.keys {
+            let invalid = |e: jsonwebtoken::errors::Error| format!("key `{}`: {e}", key.kid);
+            let decoding_key =
+                match key.algorithm {
+                    Algorithm::HS256 => DecodingKey::from_secret(key_secret(key)?.as_bytes()),
+                    Algorithm::RS256 => DecodingKey::from_rsa_pem(&read_pem(&key.public_key, key)?)
+                        .map_err(invalid)?,
+                    Algorithm::EdDSA => DecodingKey::from_ed_pem(&read_pem(&key.public_key, key)?)
+                        .map_err(invalid)?,
+                    other => {
+                        return Err(format!(
+                            "key `{}`: unsupported algorithm {other:?}",
+                            key.kid
+                        ))
+                    }
+                };
+
+            if key.kid == config.active_kid {
+                encoding_key = Some(match key.algorithm {
+                    Algorithm::HS256 => EncodingKey::from_secret(key_secret(key)?.as_bytes()),
+                    Algorithm::RS256 => {
+                        EncodingKey::from_rsa_pem(&read_pem(&key.private_key, key)?)
+                            .map_err(invalid)?
+                    }
+                    _ => EncodingKey::from_ed_pem(&read_pem(&key.private_key, key)?)
+                        .map_err(invalid)?,
+                });
+            }
+
+            if decoding_keys
+                .insert(key.kid.clone(), (key.algorithm, decoding_key))
+                .is_some()
+            {
+                return Err(format!("duplicate key id `{}`", key.kid));
+            }
+        }
+
+        let Some(encoding_key) = encoding_key else {
+            return Err(format!(
+                "active key `{}` is not configured",
+                config.active_kid
+            ));
+        };
+
+        Ok(JwtKeys {
+            issuer: config.issuer.clone(),
+            audience: config.audience.clone(),
+            lifetime: config.lifetime,
+            refresh_lifetime: config.refresh_lifetime,
+            active_kid: config.active_kid.clone(),
+            active_algorithm: decoding_keys[&config.active_kid].0,
+            encoding_key,
+            decoding_keys,
+        })
+    }
+
+    pub fn lifetime(&self) -> i64 {
+        self.lifetime
+    }
+
+    pub fn refresh_lifetime(&self) -> i64 {
+        self.refresh_lifetime
+    }
+
+    pub fn encode(
+        &self,
+        user_id: i32,
+        session_id: i32,
+    ) -> Result<String, jsonwebtoken::errors::Error> {
+        let iat = now();
+        let claims = Claims {
+            sub: user_id,
+            sid: session_id,
+            exp: iat + self.lifetime,
+            iat,
+            iss: self.issuer.clone(),
+            aud: self.audience.clone(),
+        };
+
+        let mut header = Header::new(self.active_algorithm);
+        header.kid = Some(self.active_kid.clone());
+        encode(&header, &claims, &self.encoding_key)
+    }
+
+    /// Verifies `token` against the key named by its `kid` header. Tokens without a `kid` or with
+    /// an unknown one are rejected.
+    pub fn decode(&self, token: &str) -> Option<Claims> {
+        let header = decode_header(token).ok()?;
+        let (algorithm, key) = self.decoding_keys.get(header.kid.as_deref()?)?;
+
+        let mut validation = Validation::new(*algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+        decode(token, key, &validation).ok().map(|data| data.claims)
+    }
+}
+
+fn key_secret(key: &JwtKeyConfig) -> Result<&str, String> {
+    key.secret
+        .as_deref()
+        .ok_or_else(|| format!("key `{}`: `secret` is required for HS256", key.kid))
+}
+
+fn read_pem(path: &Option<String>, key: &JwtKeyConfig) -> Result<Vec<u8>, String> {
+    let Some(path) = path else {
+        return Err(format!("key `{}`: PEM key path is missing", key.kid));
+    };
+    fs::read(path).map_err(|e| format!("key `{}`: {path}: {e}", key.kid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> Option<String> {
+        Some(format!(
+            "{}/tests/fixtures/jwt/{name}",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+    }
+
+    fn hs256(kid: &str, secret: &str) -> JwtKeyConfig {
+        JwtKeyConfig {
+            kid: kid.into(),
+            algorithm: Algorithm::HS256,
+            secret: Some(secret.into()),
+            private_key: None,
+            public_key: None,
+        }
+    }
+
+    fn pem(kid: &str, algorithm: Algorithm, name: &str) -> JwtKeyConfig {
+        JwtKeyConfig {
+            kid: kid.into(),
+            algorithm,
+            secret: None,
+            private_key: fixture(&format!("{name}.pem")),
+            public_key: fixture(&format!("{name}.pub.pem")),
+        }
+    }
+
+    fn keys(active_kid: &str, keys: Vec<JwtKeyConfig>) -> JwtKeys {
+        JwtKeys::from_config(&JwtConfig {
+            active_kid: active_kid.into(),
+            keys,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn round_trips_with_every_algorithm() {
+        for key in [
+            hs256("hs", "secret"),
+            pem("rs", Algorithm::RS256, "rs256"),
+            pem("ed", Algorithm::EdDSA, "eddsa"),
+        ] {
+            let kid = key.kid.clone();
+            let keys = keys(&kid, vec![key]);
+            let token = keys.encode(7, 3).unwrap();
+            assert_eq!(decode_header(&token).unwrap().kid.as_deref(), Some(&*kid));
+            let claims = keys.decode(&token).unwrap();
+            assert_eq!((claims.sub, claims.sid), (7, 3));
+            assert_eq!(claims.iss, "sagongsa");
+        }
+    }
+
+    #[test]
+    fn expired_tokens_are_rejected() {
+        let keys = JwtKeys::from_config(&JwtConfig {
+            // Past the default leeway of a minute.
+            lifetime: -120,
+            active_kid: "hs".into(),
+            keys: vec![hs256("hs", "secret")],
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(keys.decode(&keys.encode(1, 1).unwrap()).is_none());
+    }
+
+    #[test]
+    fn wrong_issuer_or_audience_is_rejected() {
+        let ours = keys("hs", vec![hs256("hs", "secret")]);
+        for (issuer, audience) in [("other", "sagongsa"), ("sagongsa", "other")] {
+            let theirs = JwtKeys::from_config(&JwtConfig {
+                issuer: issuer.into(),
+                audience: audience.into(),
+                active_kid: "hs".into(),
+                keys: vec![hs256("hs", "secret")],
+                ..Default::default()
+            })
+            .unwrap();
+            assert!(ours.decode(&theirs.encode(1, 1).unwrap()).is_none());
+        }
+    }
+
+    #[test]
+    fn rotated_keys_keep_verifying_old_tokens() {
+        let before = keys("2026-04", vec![hs256("2026-04", "old-secret")]);
+        let old_token = before.encode(1, 1).unwrap();
+
+        let after = keys(
+            "2026-10",
+            vec![
+                hs256("2026-04", "old-secret"),
+                pem("2026-10", Algorithm::EdDSA, "eddsa"),
+            ],
+        );
+        assert_eq!(after.decode(&old_token).unwrap().sub, 1);
+        let new_token = after.encode(2, 2).unwrap();
+        assert_eq!(
+            decode_header(&new_token).unwrap().kid.as_deref(),
+            Some("2026-10")
+        );
+        assert_eq!(after.decode(&new_token).unwrap().sub, 2);
+
+        // Keys that were never configured, or dropped later, no longer verify anything.
+        assert!(before.decode(&new_token).is_none());
+        let retired = keys("2026-10", vec![pem("2026-10", Algorithm::EdDSA, "eddsa")]);
+        assert!(retired.decode(&old_token).is_none());
+    }
+
+    #[test]
+    fn tokens_without_kid_are_rejected() {
+        let keys = keys("hs", vec![hs256("hs", "secret")]);
+        let claims = Claims {
+            sub: 1,
+            sid: 1,
+            exp: now() + 60,
+            iat: now(),
+            iss: "sagongsa".into(),
+            aud: "sagongsa".into(),
+        };
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+        assert!(keys.decode(&token).is_none());
+    }
+}
@@ -0,0 +1,221 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::DefaultBodyLimit,
+    http::HeaderValue,
+    routing::{delete, get, patch, post, put},
+    Router,
+};
+use sqlx::SqlitePool;
+use tower_http::{
+    cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer},
+    trace::TraceLayer,
+};
+
+mod applications;
+mod attachments;
+mod auth;
+mod avatars;
+mod bookmarks;
+mod comments;
+pub mod config;
+mod contests;
+mod error;
+mod extract;
+mod files;
+mod images;
+pub mod jwt;
+mod likes;
+pub mod migrate;
+mod models;
+mod pagination;
+mod posts;
+mod search;
+pub mod storage;
+mod teams;
+mod users;
+mod utils;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: SqlitePool,
+    pub config: Arc<config::Config>,
+    pub jwt: Arc<jwt::JwtKeys>,
+    files: Arc<files::Files>,
+}
+
+impl AppState {
+    pub fn new(pool: SqlitePool, config: config::Config) -> Result<AppState, String> {
+        let jwt = jwt::JwtKeys::from_config(&config.jwt)?;
+        let storage = storage::from_config(&config.storage)?;
+        Ok(AppState {
+            files: Arc::new(files::Files::new(pool.clone(), storage)),
+            pool,
+            config: Arc::new(config),
+            jwt: Arc::new(jwt),
+        })
+    }
+
+    /// Deletes uploaded files that nothing has referenced for `storage.orphan_grace` seconds,
+    /// returning how many there were.
+    pub async fn clean_up_files(&self) -> Result<usize, String> {
+        self.files
+            .clean_up(self.config.storage.orphan_grace)
+            .await
+            .map_err(|e| format!("{e:?}"))
+    }
+
+    /// Moves avatars stored in the database before migration 0012 into the storage backend,
+    /// returning how many users had one. Does nothing once they have all been moved.
+    pub async fn import_legacy_avatars(&self) -> Result<usize, String> {
+        avatars::import_legacy_avatars(self)
+            .await
+            .map_err(|e| format!("{e:?}"))
+    }
+}
+
+/// Runs [`AppState::clean_up_files`] every `storage.cleanup_interval` seconds, forever.
+pub async fn clean_up_files_periodically(state: AppState) {
+    let period = Duration::from_secs(state.config.storage.cleanup_interval);
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        match state.clean_up_files().await {
+            Ok(0) => {}
+            Ok(deleted) => tracing::info!("deleted {deleted} orphaned files"),
+            Err(e) => tracing::error!("orphaned file cleanup failed: {e}"),
+        }
+    }
+}
+
+fn cors_layer(origins: &[String]) -> CorsLayer {
+    if origins.iter().any(|origin| origin == "*") {
+        return CorsLayer::very_permissive();
+    }
+
+    let origins = origins
+        .iter()
+        .filter_map(|origin| HeaderValue::from_str(origin).ok())
+        .collect::<Vec<_>>();
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods(AllowMethods::mirror_request())
+        .allow_headers(AllowHeaders::mirror_request())
+        .allow_credentials(true)
+}
+
+pub fn router(state: AppState) -> Router {
+    let cors = cors_layer(&state.config.cors_origins);
+    let body_limit = DefaultBodyLimit::max(state.config.upload.max_bytes);
+
+    Router::new()
+        .route("/signup", post(auth::signup))
+        .route("/login", post(auth::login))
+        .route("/token/refresh", post(auth::refresh))
+        .route("/logout", post(auth::logout))
+        .route("/users/@me", get(users::me))
+        .route("/users/@me", patch(users::update_me))
+        .route("/users/@me/password", put(users::change_password))
+        .route("/users/@me/email", put(users::change_email))
+        .route("/users/@me/avatar", put(avatars::upload_avatar))
+        .route("/users/@me/avatar", delete(avatars::delete_avatar))
+        .route("/users/@me/sessions", get(users::list_sessions))
+        .route(
+            "/users/@me/sessions/:session_id",
+            delete(users::delete_session),
+        )
+        .route("/users/@me/bookmarks", get(bookmarks::list_bookmarks))
+        .route("/users", get(users::list_users))
+        .route("/users/:user_id", get(users::get_user))
+        .route("/users/:user_id/avatar", get(avatars::get_avatar))
+        .route("/users/:user_id/teams", get(teams::list_user_teams))
+        .route("/contests", get(contests::list_contests))
+        .route("/contests/:contest_id", get(contests::get_contest))
+        .route("/contests", post(contests::create_contest))
+        .route("/contests", delete(contests::delete_contests))
+        .route("/contests/:contest_id", patch(contests::update_contest))
+        .route("/contests/:contest_id", delete(contests::delete_contest))
+        .route("/contests/:contest_id/poster", put(contests::upload_poster))
+        .route(
+            "/contests/:contest_id/poster",
+            delete(contests::delete_poster),
+        )
+        .route(
+            "/contests/:contest_id/posts",
+            get(contests::list_linked_posts),
+        )
+        .route("/contests/:contest_id/like", put(likes::like_contest))
+        .route("/contests/:contest_id/like", delete(likes::unlike_contest))
+        .route(
+            "/contests/:contest_id/bookmark",
+            put(bookmarks::bookmark_contest),
+        )
+        .route(
+            "/contests/:contest_id/bookmark",
+            delete(bookmarks::unbookmark_contest),
+        )
+        .route("/posts", get(posts::list_posts))
+        .route("/posts/:post_id", get(posts::get_post))
+        .route("/posts", post(posts::create_post))
+        .route("/posts", delete(posts::delete_posts))
+        .route("/posts/:post_id", patch(posts::update_post))
+        .route("/posts/:post_id", delete(posts::delete_post))
+        .route("/posts/:post_id/like", put(likes::like_post))
+        .route("/posts/:post_id/like", delete(likes::unlike_post))
+        .route("/posts/:post_id/bookmark", put(bookmarks::bookmark_post))
+        .route(
+            "/posts/:post_id/bookmark",
+            delete(bookmarks::unbookmark_post),
+        )
+        .route("/posts/:post_id/comments", get(comments::list_comments))
+        .route("/posts/:post_id/comments", post(comments::create_comment))
+        .route(
+            "/posts/:post_id/comments/:comment_id",
+            patch(comments::update_comment),
+        )
+        .route(
+            "/posts/:post_id/comments/:comment_id",
+            delete(comments::delete_comment),
+        )
+        .route(
+            "/posts/:post_id/applications",
+            get(applications::list_applications),
+        )
+        .route("/posts/:post_id/applications", post(applications::apply))
+        .route(
+            "/posts/:post_id/applications/:application_id",
+            delete(applications::withdraw_application),
+        )
+        .route(
+            "/posts/:post_id/applications/:application_id/accept",
+            post(applications::accept_application),
+        )
+        .route(
+            "/posts/:post_id/applications/:application_id/reject",
+            post(applications::reject_application),
+        )
+        .route(
+            "/posts/:post_id/attachments",
+            get(attachments::list_attachments),
+        )
+        .route(
+            "/posts/:post_id/attachments",
+            post(attachments::upload_attachment),
+        )
+        .route(
+            "/posts/:post_id/attachments/:attachment_id",
+            delete(attachments::delete_attachment),
+        )
+        .route("/posts/:post_id/team", post(teams::create_team))
+        .route("/teams/:team_id", get(teams::get_team))
+        .route("/teams/:team_id", delete(teams::disband_team))
+        .route("/teams/:team_id/leader", put(teams::transfer_leadership))
+        .route("/teams/:team_id/members/@me", delete(teams::leave_team))
+        .route("/search", get(search::search))
+        .route("/files/*key", get(files::get_file))
+        .fallback(|| async { error::AppError::not_found("No such route") })
+        .layer(body_limit)
+        .layer(cors)
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
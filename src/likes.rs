@@ -0,0 +1,69 @@
+use axum::extract::State;
+use serde::Serialize;
+
+use crate::{
+    auth::Auth,
+    error::AppError,
+    extract::{Json, Path},
+    models::{ItemKind, Like},
+    utils::now,
+    AppState,
+};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LikeResponse {
+    like_count: i32,
+    liked_by_me: bool,
+}
+
+async fn set_like(
+    state: &AppState,
+    user_id: i32,
+    kind: ItemKind,
+    item_id: i32,
+    liked: bool,
+) -> Result<Json<LikeResponse>, AppError> {
+    match Like::set(&state.pool, user_id, kind, item_id, liked, now()).await? {
+        Some(like_count) => Ok(Json(LikeResponse {
+            like_count,
+            liked_by_me: liked,
+        })),
+        None => Err(AppError::not_found(match kind {
+            ItemKind::Contest => "Contest not found",
+            ItemKind::Post => "Post not found",
+        })),
+    }
+}
+
+pub async fn like_contest(
+    State(state): State<AppState>,
+    Path(contest_id): Path<i32>,
+    Auth(auth): Auth,
+) -> Result<Json<LikeResponse>, AppError> {
+    set_like(&state, auth.sub, ItemKind::Contest, contest_id, true).await
+}
+
+pub async fn unlike_contest(
+    State(state): State<AppState>,
+    Path(contest_id): Path<i32>,
+    Auth(auth): Auth,
+) -> Result<Json<LikeResponse>, AppError> {
+    set_like(&state, auth.sub, ItemKind::Contest, contest_id, false).await
+}
+
+pub async fn like_post(
+    State(state): State<AppState>,
+    Path(post_id): Path<i32>,
+    Auth(auth): Auth,
+) -> Result<Json<LikeResponse>, AppError> {
+    set_like(&state, auth.sub, ItemKind::Post, post_id, true).await
+}
+
+pub async fn unlike_post(
+    State(state): State<AppState>,
+    Path(post_id): Path<i32>,
+    Auth(auth): Auth,
+) -> Result<Json<LikeResponse>, AppError> {
+    set_like(&state, auth.sub, ItemKind::Post, post_id, false).await
+}
@@ -1,129 +1,137 @@
-use axum::{
-    routing::{delete, get, post},
-    Router,
+use std::{env, net::SocketAddr, process::ExitCode, str::FromStr};
+
+use sagongsa_server::{clean_up_files_periodically, config::Config, migrate, router, AppState};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    SqlitePool,
 };
-use sqlx::SqlitePool;
 use tokio::net::TcpListener;
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
-use tracing_subscriber::filter::LevelFilter;
-
-mod auth;
-mod comments;
-mod contests;
-mod models;
-mod posts;
-mod users;
-mod utils;
-
-const DATABASE_URL: &str = "sqlite:main.db";
-
-#[derive(Clone)]
-struct AppState {
-    pool: SqlitePool,
-}
+use tracing_subscriber::EnvFilter;
+
+const USAGE: &str = "\
+Usage: sagongsa-server [--config <path>] [migrate <status | up [N] | down [N]>]
+
+Without a command, applies pending migrations and starts the server.";
 
 #[tokio::main]
-async fn main() {
-    let pool = SqlitePool::connect(DATABASE_URL).await.unwrap();
-    init_tables(&pool).await;
+async fn main() -> ExitCode {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    let config_path = match args.iter().position(|arg| arg == "--config") {
+        Some(i) if i + 1 < args.len() => Some(args.drain(i..=i + 1).nth(1).unwrap()),
+        Some(_) => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+
+    let config = match Config::load(config_path.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let options = SqliteConnectOptions::from_str(&config.database_url)
+        .expect("Invalid database URL")
+        .create_if_missing(true);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(config.pool_size)
+        .connect_with(options)
+        .await
+        .expect("Failed to open database");
+
+    match args.first().map(String::as_str) {
+        None => {}
+        Some("migrate") => return run_migrate(&pool, &args[1..]).await,
+        Some(_) => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    migrate::up(&pool, None)
+        .await
+        .expect("Failed to apply migrations");
 
     tracing_subscriber::fmt::fmt()
-        .with_max_level(LevelFilter::DEBUG)
+        .with_env_filter(EnvFilter::new(&config.log_filter))
         .init();
 
-    let router = Router::new()
-        .route("/signup", post(auth::signup))
-        .route("/login", post(auth::login))
-        .route("/users/@me", get(users::me))
-        .route("/users", get(users::list_users))
-        .route("/users/:user_id", get(users::get_user))
-        .route("/contests", get(contests::list_contests))
-        .route("/contests/:contest_id", get(contests::get_contest))
-        .route("/contests", post(contests::create_contest))
-        .route("/contests", delete(contests::delete_contests))
-        .route(
-            "/contests/:contest_id/posts",
-            get(contests::list_linked_posts),
-        )
-        .route("/posts", get(posts::list_posts))
-        .route("/posts/:post_id", get(posts::get_post))
-        .route("/posts", post(posts::create_post))
-        .route("/posts", delete(posts::delete_posts))
-        .route("/posts/:post_id/comments", get(comments::list_comments))
-        .route("/posts/:post_id/comments", post(comments::create_comment))
-        .layer(CorsLayer::very_permissive())
-        .layer(TraceLayer::new_for_http())
-        .with_state(AppState { pool });
-
-    let listener = TcpListener::bind("0.0.0.0:4000")
+    let listen_addr = config.listen_addr;
+    let state = match AppState::new(pool, config) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("invalid configuration: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Retried on the next start if it fails; until then those users show no avatar.
+    match state.import_legacy_avatars().await {
+        Ok(0) => {}
+        Ok(users) => tracing::info!("moved the avatars of {users} users into storage"),
+        Err(e) => tracing::error!("moving avatars into storage failed: {e}"),
+    }
+    tokio::spawn(clean_up_files_periodically(state.clone()));
+
+    let listener = TcpListener::bind(listen_addr)
         .await
         .expect("Failed to bind port");
-    axum::serve(listener, router.into_make_service())
-        .await
-        .unwrap();
-}
-
-async fn init_tables(pool: &SqlitePool) {
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS users (
-            id INTEGER PRIMARY KEY,
-            username VARCHAR(10) NOT NULL,
-            password VARCHAR(20) NOT NULL,
-            nickname VARCHAR(10) NOT NULL,
-            email VARCHAR(100) NOT NULL,
-            bio VARCHAR(1000),
-            is_manager BOOLEAN NOT NULL,
-            is_withdrawn BOOLEAN NOT NULL,
-            field INTEGER,
-            profile_img BLOB
-        );
-
-        CREATE TABLE IF NOT EXISTS contests (
-            contest_id INTEGER PRIMARY KEY,
-            user_id INTEGER NOT NULL,
-            title VARCHAR(100) NOT NULL,
-            img VARCHAR(1000),
-            ratio VARCHAR(100) NOT NULL,
-            prize VARCHAR(100) NOT NULL,
-            started_at DATETIME NOT NULL,
-            ended_at DATETIME NOT NULL,
-            link VARCHAR(1000) NOT NULL,
-            field INTEGER NOT NULL,
-            like_count INTEGER NOT NULL,
-            FOREIGN KEY (user_id) REFERENCES users(id)
-        );
-
-        CREATE TABLE IF NOT EXISTS posts (
-            post_id INTEGER PRIMARY KEY,
-            user_id INTEGER NOT NULL,
-            contest_id INTEGER,
-            title VARCHAR(100) NOT NULL,
-            content VARCHAR(1000),
-            max INTEGER,
-            ppl INTEGER,
-            desired_field INTEGER,
-            created_at DATETIME NOT NULL,
-            ended_at DATETIME NOT NULL,
-            like_count INTEGER NOT NULL,
-            FOREIGN KEY (user_id) REFERENCES users(id),
-            FOREIGN KEY (contest_id) REFERENCES contests(contest_id)
-        );
-
-        CREATE TABLE IF NOT EXISTS comments (
-            comment_id INTEGER PRIMARY KEY,
-            post_id INTEGER NOT NULL,
-            user_id INTEGER NOT NULL,
-            content VARCHAR NOT NULL,
-            created_at DATETIME NOT NULL,
-            edited_at DATETIME,
-            parent INTEGER,
-            FOREIGN KEY (post_id) REFERENCES posts(post_id),
-            FOREIGN KEY (user_id) REFERENCES users(id)
-        );
-        "#,
+    axum::serve(
+        listener,
+        router(state).into_make_service_with_connect_info::<SocketAddr>(),
     )
-    .execute(pool)
     .await
-    .expect("Failed to create tables");
+    .unwrap();
+
+    ExitCode::SUCCESS
+}
+
+async fn run_migrate(pool: &SqlitePool, args: &[String]) -> ExitCode {
+    let steps = match args.get(1).map(|steps| steps.parse::<usize>()) {
+        None => None,
+        Some(Ok(steps)) => Some(steps),
+        Some(Err(_)) => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let result = match args.first().map(String::as_str) {
+        Some("status") => migrate::status(pool).await.map(|statuses| {
+            for status in statuses {
+                let state = match status.applied_at {
+                    Some(applied_at) => format!("applied at {applied_at}"),
+                    None => "pending".to_string(),
+                };
+                println!("{:>4} {:<30} {state}", status.version, status.description);
+            }
+        }),
+        Some("up") => migrate::up(pool, steps).await.map(|versions| {
+            for version in versions {
+                println!("applied {version}");
+            }
+        }),
+        Some("down") => migrate::down(pool, steps.unwrap_or(1))
+            .await
+            .map(|versions| {
+                for version in versions {
+                    println!("reverted {version}");
+                }
+            }),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("migrate: {e}");
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,139 @@
+use sqlx::{
+    migrate::{MigrateError, Migration, MigrationType, Migrator},
+    Acquire, Executor, SqlitePool,
+};
+
+use crate::utils::now;
+
+/// Migrations under `migrations/`, embedded at compile time.
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied_at: Option<i64>,
+}
+
+struct AppliedMigration {
+    version: i64,
+    checksum: Vec<u8>,
+    applied_at: i64,
+}
+
+async fn ensure_table(pool: &SqlitePool) -> Result<(), MigrateError> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS _migrations (
+            version INTEGER PRIMARY KEY,
+            description VARCHAR(100) NOT NULL,
+            checksum BLOB NOT NULL,
+            applied_at DATETIME NOT NULL
+        );
+        "#,
+    )
+    .await?;
+    Ok(())
+}
+
+async fn applied(pool: &SqlitePool) -> Result<Vec<AppliedMigration>, MigrateError> {
+    ensure_table(pool).await?;
+    let rows: Vec<(i64, Vec<u8>, i64)> =
+        sqlx::query_as("SELECT version, checksum, applied_at FROM _migrations ORDER BY version")
+            .fetch_all(pool)
+            .await?;
+
+    let applied = rows
+        .into_iter()
+        .map(|(version, checksum, applied_at)| AppliedMigration {
+            version,
+            checksum,
+            applied_at,
+        })
+        .collect::<Vec<_>>();
+
+    // Refuse to touch a database whose history doesn't match the embedded migrations.
+    for migration in &applied {
+        match up_migrations().find(|m| m.version == migration.version) {
+            Some(m) if *m.checksum != *migration.checksum => {
+                return Err(MigrateError::VersionMismatch(migration.version))
+            }
+            Some(_) => {}
+            None => return Err(MigrateError::VersionMissing(migration.version)),
+        }
+    }
+
+    Ok(applied)
+}
+
+fn up_migrations() -> impl Iterator<Item = &'static Migration> {
+    MIGRATOR
+        .iter()
+        .filter(|m| m.migration_type != MigrationType::ReversibleDown)
+}
+
+pub async fn status(pool: &SqlitePool) -> Result<Vec<MigrationStatus>, MigrateError> {
+    let applied = applied(pool).await?;
+    Ok(up_migrations()
+        .map(|m| MigrationStatus {
+            version: m.version,
+            description: m.description.to_string(),
+            applied_at: applied
+                .iter()
+                .find(|a| a.version == m.version)
+                .map(|a| a.applied_at),
+        })
+        .collect())
+}
+
+/// Applies up to `steps` pending migrations in version order, or all of them for `None`.
+/// Returns the applied versions.
+pub async fn up(pool: &SqlitePool, steps: Option<usize>) -> Result<Vec<i64>, MigrateError> {
+    let applied = applied(pool).await?;
+    let pending = up_migrations()
+        .filter(|m| !applied.iter().any(|a| a.version == m.version))
+        .take(steps.unwrap_or(usize::MAX));
+
+    let mut versions = Vec::new();
+    for migration in pending {
+        let mut conn = pool.acquire().await?;
+        let mut tx = conn.begin().await?;
+        (&mut *tx).execute(&*migration.sql).await?;
+        sqlx::query(
+            "INSERT INTO _migrations (version, description, checksum, applied_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(migration.version)
+        .bind(&*migration.description)
+        .bind(&*migration.checksum)
+        .bind(now())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        versions.push(migration.version);
+    }
+    Ok(versions)
+}
+
+/// Reverts the `steps` most recently applied migrations. Returns the reverted versions.
+pub async fn down(pool: &SqlitePool, steps: usize) -> Result<Vec<i64>, MigrateError> {
+    let applied = applied(pool).await?;
+
+    let mut versions = Vec::new();
+    for migration in applied.iter().rev().take(steps) {
+        let Some(down) = MIGRATOR.iter().find(|m| {
+            m.version == migration.version && m.migration_type == MigrationType::ReversibleDown
+        }) else {
+            return Err(MigrateError::VersionNotPresent(migration.version));
+        };
+
+        let mut conn = pool.acquire().await?;
+        let mut tx = conn.begin().await?;
+        (&mut *tx).execute(&*down.sql).await?;
+        sqlx::query("DELETE FROM _migrations WHERE version = ?")
+            .bind(migration.version)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        versions.push(migration.version);
+    }
+    Ok(versions)
+}
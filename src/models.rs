@@ -1,8 +1,13 @@
+use std::collections::HashSet;
+
 use serde::{Deserialize, Serialize};
-use sqlx::{prelude::FromRow, SqlitePool};
+use sqlx::{prelude::FromRow, QueryBuilder, Sqlite, SqlitePool};
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize, FromRow)]
-#[serde(rename_all = "camelCase")]
+use crate::pagination::Cursor;
+
+/// A row of `users`. Deliberately not `Serialize`: responses go through
+/// [`PublicUser`](crate::users::PublicUser) or [`PrivateUser`](crate::users::PrivateUser).
+#[derive(Clone, Debug, Default, FromRow)]
 pub struct User {
     pub id: i32,
     pub username: String,
@@ -13,15 +18,16 @@ pub struct User {
     pub is_manager: bool,
     pub is_withdrawn: bool,
     pub field: i32,
-    pub profile_img: Option<Vec<u8>>,
+    /// Content hash of the current avatar, `None` if the user has not uploaded one.
+    pub avatar_hash: Option<String>,
 }
 
 impl User {
-    pub async fn insert(pool: &SqlitePool, user: &User) {
+    pub async fn insert(pool: &SqlitePool, user: &User) -> Result<i64, sqlx::Error> {
         sqlx::query(
             r#"
-            INSERT INTO users (id, pw, nickname, email, bio, is_manager, is_withdrawn, field, profile_img)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO users (username, password, nickname, email, bio, is_manager, is_withdrawn, field)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(&user.username)
@@ -32,34 +38,342 @@ impl User {
         .bind(user.is_manager)
         .bind(user.is_withdrawn)
         .bind(user.field)
-        .bind(&user.profile_img)
         .execute(pool)
         .await
-        .unwrap();
+        .map(|result| result.last_insert_rowid())
     }
 
-    pub async fn find_all(pool: &SqlitePool) -> Vec<User> {
+    pub async fn find_all(pool: &SqlitePool) -> Result<Vec<User>, sqlx::Error> {
         sqlx::query_as::<_, User>("SELECT * FROM users")
             .fetch_all(pool)
             .await
-            .unwrap()
     }
 
-    pub async fn find_by_username(pool: &SqlitePool, username: &str) -> Option<User> {
-        dbg!(&username);
+    pub async fn find_by_username(
+        pool: &SqlitePool,
+        username: &str,
+    ) -> Result<Option<User>, sqlx::Error> {
         sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
             .bind(username)
             .fetch_optional(pool)
             .await
-            .unwrap()
     }
 
-    pub async fn find_by_id(pool: &SqlitePool, id: i32) -> Option<User> {
+    pub async fn find_by_id(pool: &SqlitePool, id: i32) -> Result<Option<User>, sqlx::Error> {
         sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
             .bind(id)
             .fetch_optional(pool)
             .await
-            .unwrap()
+    }
+
+    pub async fn update_password(
+        pool: &SqlitePool,
+        id: i32,
+        password: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET password = ? WHERE id = ?")
+            .bind(password)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Saves the profile fields of `user`: nickname, bio and field.
+    pub async fn update_profile(pool: &SqlitePool, user: &User) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET nickname = ?, bio = ?, field = ? WHERE id = ?")
+            .bind(&user.nickname)
+            .bind(&user.bio)
+            .bind(user.field)
+            .bind(user.id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn update_email(pool: &SqlitePool, id: i32, email: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET email = ? WHERE id = ?")
+            .bind(email)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// One size of a user's avatar, stored as a file.
+#[derive(Clone, Debug, Default, FromRow)]
+pub struct Avatar {
+    pub user_id: i32,
+    /// Width and height in pixels.
+    pub size: i32,
+    pub key: String,
+    /// From the file's `files` row.
+    pub content_type: String,
+}
+
+impl Avatar {
+    /// Replaces every size of `user_id`'s avatar with `avatars` and records `hash` as current.
+    pub async fn replace(
+        pool: &SqlitePool,
+        user_id: i32,
+        hash: &str,
+        avatars: &[Avatar],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM avatars WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        for avatar in avatars {
+            sqlx::query("INSERT INTO avatars (user_id, size, key) VALUES (?, ?, ?)")
+                .bind(avatar.user_id)
+                .bind(avatar.size)
+                .bind(&avatar.key)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query("UPDATE users SET avatar_hash = ? WHERE id = ?")
+            .bind(hash)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+
+    pub async fn delete(pool: &SqlitePool, user_id: i32) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM avatars WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE users SET avatar_hash = NULL WHERE id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+
+    pub async fn find(
+        pool: &SqlitePool,
+        user_id: i32,
+        size: i32,
+    ) -> Result<Option<Avatar>, sqlx::Error> {
+        sqlx::query_as::<_, Avatar>(
+            r#"
+            SELECT avatars.*, files.content_type
+            FROM avatars
+            JOIN files ON files.key = avatars.key
+            WHERE avatars.user_id = ? AND avatars.size = ?
+            "#,
+        )
+        .bind(user_id)
+        .bind(size)
+        .fetch_optional(pool)
+        .await
+    }
+}
+
+/// One size of an avatar uploaded before files moved to the storage backend, still holding its
+/// bytes. Moved into storage on startup.
+#[derive(Clone, Debug, Default, FromRow)]
+pub struct LegacyAvatar {
+    pub user_id: i32,
+    pub size: i32,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl LegacyAvatar {
+    pub async fn find_user_ids(pool: &SqlitePool) -> Result<Vec<i32>, sqlx::Error> {
+        sqlx::query_scalar("SELECT DISTINCT user_id FROM legacy_avatars ORDER BY user_id")
+            .fetch_all(pool)
+            .await
+    }
+
+    pub async fn find_by_user_id(
+        pool: &SqlitePool,
+        user_id: i32,
+    ) -> Result<Vec<LegacyAvatar>, sqlx::Error> {
+        sqlx::query_as::<_, LegacyAvatar>("SELECT * FROM legacy_avatars WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+    }
+
+    /// Replaces `user_id`'s legacy rows with `avatars`, whose files are already stored. Sizes the
+    /// user has uploaded again since are kept.
+    pub async fn migrate(
+        pool: &SqlitePool,
+        user_id: i32,
+        avatars: &[Avatar],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        for avatar in avatars {
+            sqlx::query("INSERT OR IGNORE INTO avatars (user_id, size, key) VALUES (?, ?, ?)")
+                .bind(avatar.user_id)
+                .bind(avatar.size)
+                .bind(&avatar.key)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query("DELETE FROM legacy_avatars WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+}
+
+/// A file in the storage backend. Rows are written before anything references the file and
+/// deleted by orphan cleanup once nothing has for a while.
+#[derive(Clone, Debug, Default, FromRow)]
+pub struct StoredFile {
+    pub key: String,
+    pub content_type: String,
+    pub size: i64,
+    pub created_at: i64,
+}
+
+/// Every column that references `files(key)`. Orphan cleanup keeps a file while any of them does.
+const FILE_REFERENCES: &str = r#"
+    NOT EXISTS (SELECT 1 FROM avatars WHERE avatars.key = files.key)
+    AND NOT EXISTS (SELECT 1 FROM attachments WHERE attachments.key = files.key)
+    AND NOT EXISTS (SELECT 1 FROM contests WHERE contests.poster_key = files.key)
+    AND NOT EXISTS (SELECT 1 FROM contests WHERE contests.poster_card_key = files.key)
+    AND NOT EXISTS (SELECT 1 FROM contests WHERE contests.poster_thumbnail_key = files.key)
+"#;
+
+impl StoredFile {
+    /// Records `file`. Storing content that already exists refreshes `created_at`, which restarts
+    /// its grace period before cleanup.
+    pub async fn upsert(pool: &SqlitePool, file: &StoredFile) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO files (key, content_type, size, created_at) VALUES (?, ?, ?, ?)
+            ON CONFLICT (key) DO UPDATE SET created_at = excluded.created_at
+            "#,
+        )
+        .bind(&file.key)
+        .bind(&file.content_type)
+        .bind(file.size)
+        .bind(file.created_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn find(pool: &SqlitePool, key: &str) -> Result<Option<StoredFile>, sqlx::Error> {
+        sqlx::query_as::<_, StoredFile>("SELECT * FROM files WHERE key = ?")
+            .bind(key)
+            .fetch_optional(pool)
+            .await
+    }
+
+    /// Keys of files created no later than `before` that nothing references.
+    pub async fn find_orphans(pool: &SqlitePool, before: i64) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(&format!(
+            "SELECT key FROM files WHERE created_at <= ? AND {FILE_REFERENCES}"
+        ))
+        .bind(before)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Deletes the row for `key` if it is still an orphan created no later than `before`.
+    /// Returns whether it was.
+    pub async fn delete_orphan(
+        pool: &SqlitePool,
+        key: &str,
+        before: i64,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query(&format!(
+            "DELETE FROM files WHERE key = ? AND created_at <= ? AND {FILE_REFERENCES}"
+        ))
+        .bind(key)
+        .bind(before)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() == 1)
+    }
+}
+
+/// A file attached to a post.
+#[derive(Clone, Debug, Default, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub attachment_id: i32,
+    pub post_id: i32,
+    pub user_id: i32,
+    #[serde(skip)]
+    pub key: String,
+    /// The name it was uploaded with, for downloads.
+    pub filename: String,
+    pub created_at: i64,
+    /// From the file's `files` row.
+    pub content_type: String,
+    pub size: i64,
+}
+
+impl Attachment {
+    pub async fn insert(pool: &SqlitePool, attachment: &Attachment) -> Result<i64, sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO attachments (post_id, user_id, key, filename, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(attachment.post_id)
+        .bind(attachment.user_id)
+        .bind(&attachment.key)
+        .bind(&attachment.filename)
+        .bind(attachment.created_at)
+        .execute(pool)
+        .await
+        .map(|result| result.last_insert_rowid())
+    }
+
+    pub async fn find_by_post_id(
+        pool: &SqlitePool,
+        post_id: i32,
+    ) -> Result<Vec<Attachment>, sqlx::Error> {
+        sqlx::query_as::<_, Attachment>(
+            r#"
+            SELECT attachments.*, files.content_type, files.size
+            FROM attachments
+            JOIN files ON files.key = attachments.key
+            WHERE post_id = ?
+            ORDER BY attachment_id
+            "#,
+        )
+        .bind(post_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn find_by_id(
+        pool: &SqlitePool,
+        attachment_id: i32,
+    ) -> Result<Option<Attachment>, sqlx::Error> {
+        sqlx::query_as::<_, Attachment>(
+            r#"
+            SELECT attachments.*, files.content_type, files.size
+            FROM attachments
+            JOIN files ON files.key = attachments.key
+            WHERE attachment_id = ?
+            "#,
+        )
+        .bind(attachment_id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    pub async fn delete(pool: &SqlitePool, attachment_id: i32) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM attachments WHERE attachment_id = ?")
+            .bind(attachment_id)
+            .execute(pool)
+            .await?;
+        Ok(())
     }
 }
 
@@ -75,16 +389,54 @@ pub struct Contest {
     pub link: String,
     pub field: i32,
     pub img: Option<String>,
-    pub ratio: String,
+    /// `None` for contests from before ratios were restricted whose ratio was something else.
+    pub ratio: Option<PosterRatio>,
     pub like_count: i32,
+    /// Storage key of an uploaded poster at full size, whose URL is then in `img`.
+    #[serde(skip)]
+    pub poster_key: Option<String>,
+    /// Smaller renditions of the uploaded poster, set together with `poster_key`.
+    #[serde(skip)]
+    pub poster_card_key: Option<String>,
+    #[serde(skip)]
+    pub poster_thumbnail_key: Option<String>,
+}
+
+/// Width to height ratio of a contest's poster.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, sqlx::Type)]
+pub enum PosterRatio {
+    #[serde(rename = "1:1")]
+    #[sqlx(rename = "1:1")]
+    Square,
+    #[default]
+    #[serde(rename = "3:4")]
+    #[sqlx(rename = "3:4")]
+    Portrait,
+    #[serde(rename = "16:9")]
+    #[sqlx(rename = "16:9")]
+    Wide,
+}
+
+impl PosterRatio {
+    /// Width and height in lowest terms.
+    pub fn parts(self) -> (u32, u32) {
+        match self {
+            PosterRatio::Square => (1, 1),
+            PosterRatio::Portrait => (3, 4),
+            PosterRatio::Wide => (16, 9),
+        }
+    }
 }
 
 impl Contest {
-    pub async fn insert(pool: &SqlitePool, contest: &Contest) -> i64 {
+    pub async fn insert(pool: &SqlitePool, contest: &Contest) -> Result<i64, sqlx::Error> {
         sqlx::query(
             r#"
-            INSERT INTO contests (user_id, title, prize, started_at, ended_at, link, field, img, ratio, like_count)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO contests (
+                user_id, title, prize, started_at, ended_at, link, field, img, ratio, legacy_ratio,
+                like_count, poster_key, poster_card_key, poster_thumbnail_key
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, COALESCE(?, ''), ?, ?, ?, ?)
             "#,
         )
         .bind(contest.user_id)
@@ -95,27 +447,209 @@ impl Contest {
         .bind(&contest.link)
         .bind(contest.field)
         .bind(&contest.img)
-        .bind(&contest.ratio)
+        .bind(contest.ratio)
+        // `legacy_ratio` keeps the NOT NULL of the old `ratio` column; new rows just repeat it.
+        .bind(contest.ratio)
         .bind(contest.like_count)
+        .bind(&contest.poster_key)
+        .bind(&contest.poster_card_key)
+        .bind(&contest.poster_thumbnail_key)
         .execute(pool)
         .await
-        .unwrap()
-        .last_insert_rowid()
+        .map(|result| result.last_insert_rowid())
     }
 
-    pub async fn find_all(pool: &SqlitePool) -> Vec<Contest> {
-        sqlx::query_as::<_, Contest>("SELECT * FROM contests")
-            .fetch_all(pool)
-            .await
-            .unwrap()
+    /// Returns up to `limit` contests matching `filter` in `sort` order, starting after `after`.
+    pub async fn search(
+        pool: &SqlitePool,
+        filter: &ContestFilter,
+        sort: ContestSort,
+        after: Option<Cursor>,
+        limit: i64,
+    ) -> Result<Vec<Contest>, sqlx::Error> {
+        let mut query = QueryBuilder::new("SELECT * FROM contests WHERE 1 = 1");
+        filter.push_conditions(&mut query);
+        push_page(
+            &mut query,
+            sort.column(),
+            "contest_id",
+            sort.descending(),
+            after,
+            limit,
+        );
+        query.build_query_as().fetch_all(pool).await
     }
 
-    pub async fn find_by_id(pool: &SqlitePool, contest_id: i32) -> Option<Contest> {
+    /// Counts every contest matching `filter`, ignoring pagination.
+    pub async fn count(pool: &SqlitePool, filter: &ContestFilter) -> Result<i64, sqlx::Error> {
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM contests WHERE 1 = 1");
+        filter.push_conditions(&mut query);
+        query.build_query_scalar().fetch_one(pool).await
+    }
+
+    pub async fn find_by_id(
+        pool: &SqlitePool,
+        contest_id: i32,
+    ) -> Result<Option<Contest>, sqlx::Error> {
         sqlx::query_as::<_, Contest>("SELECT * FROM contests WHERE contest_id = ?")
             .bind(contest_id)
             .fetch_optional(pool)
             .await
-            .unwrap()
+    }
+
+    /// Saves the editable fields of `contest`.
+    pub async fn update(pool: &SqlitePool, contest: &Contest) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE contests
+            SET title = ?, prize = ?, started_at = ?, ended_at = ?, link = ?, field = ?, img = ?, ratio = ?,
+                poster_key = ?, poster_card_key = ?, poster_thumbnail_key = ?
+            WHERE contest_id = ?
+            "#,
+        )
+        .bind(&contest.title)
+        .bind(&contest.prize)
+        .bind(contest.started_at)
+        .bind(contest.ended_at)
+        .bind(&contest.link)
+        .bind(contest.field)
+        .bind(&contest.img)
+        .bind(contest.ratio)
+        .bind(&contest.poster_key)
+        .bind(&contest.poster_card_key)
+        .bind(&contest.poster_thumbnail_key)
+        .bind(contest.contest_id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Deletes the contest. Posts recruiting for it and teams formed for it are kept but
+    /// detached, so posts read as general recruitment posts from then on.
+    pub async fn delete(pool: &SqlitePool, contest_id: i32) -> Result<(), sqlx::Error> {
+        cascade(pool, &CONTEST_CASCADE, Some(contest_id)).await
+    }
+
+    /// Deletes every contest, detaching posts and teams as [`Contest::delete`] does.
+    pub async fn delete_all(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        cascade(pool, &CONTEST_CASCADE, None).await
+    }
+}
+
+/// What deleting contests takes with it, ending with the contests themselves. Each statement is
+/// completed by [`cascade`] with a condition on the contest id.
+const CONTEST_CASCADE: [&str; 5] = [
+    "UPDATE posts SET contest_id = NULL WHERE contest_id",
+    "UPDATE teams SET contest_id = NULL WHERE contest_id",
+    "DELETE FROM likes WHERE item_kind = 'contest' AND item_id",
+    "DELETE FROM bookmarks WHERE item_kind = 'contest' AND item_id",
+    "DELETE FROM contests WHERE contest_id",
+];
+
+/// Conditions for [`Contest::search`]. Unset fields match every contest.
+#[derive(Default)]
+pub struct ContestFilter {
+    pub field: Option<i32>,
+    pub status: Option<ContestStatus>,
+    /// Substring of `prize`, matched case-insensitively for ASCII.
+    pub prize: Option<String>,
+    /// Substring of `title`, matched case-insensitively for ASCII.
+    pub title: Option<String>,
+    pub now: i64,
+}
+
+/// Where a contest stands relative to `now`.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContestStatus {
+    /// Not started yet.
+    Upcoming,
+    /// Started and not yet ended.
+    Ongoing,
+    /// Past `ended_at`.
+    Closed,
+}
+
+impl ContestFilter {
+    fn push_conditions(&self, query: &mut QueryBuilder<'_, Sqlite>) {
+        if let Some(field) = self.field {
+            query.push(" AND field = ").push_bind(field);
+        }
+        match self.status {
+            Some(ContestStatus::Upcoming) => {
+                query.push(" AND started_at > ").push_bind(self.now);
+            }
+            Some(ContestStatus::Ongoing) => {
+                query
+                    .push(" AND started_at <= ")
+                    .push_bind(self.now)
+                    .push(" AND ended_at > ")
+                    .push_bind(self.now);
+            }
+            Some(ContestStatus::Closed) => {
+                query.push(" AND ended_at <= ").push_bind(self.now);
+            }
+            None => {}
+        }
+        if let Some(prize) = &self.prize {
+            query
+                .push(" AND prize LIKE ")
+                .push_bind(like_pattern(prize))
+                .push(r" ESCAPE '\'");
+        }
+        if let Some(title) = &self.title {
+            query
+                .push(" AND title LIKE ")
+                .push_bind(like_pattern(title))
+                .push(r" ESCAPE '\'");
+        }
+    }
+}
+
+/// Order of a contest listing. Ties are broken by `contest_id` in the same direction.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContestSort {
+    /// Soonest `ended_at` first.
+    #[default]
+    Deadline,
+    /// Earliest `started_at` first.
+    Start,
+    MostLiked,
+}
+
+impl ContestSort {
+    pub fn name(self) -> &'static str {
+        match self {
+            ContestSort::Deadline => "deadline",
+            ContestSort::Start => "start",
+            ContestSort::MostLiked => "mostLiked",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            ContestSort::Deadline => "ended_at",
+            ContestSort::Start => "started_at",
+            ContestSort::MostLiked => "like_count",
+        }
+    }
+
+    fn descending(self) -> bool {
+        matches!(self, ContestSort::MostLiked)
+    }
+
+    /// Position of `contest` in this order, for building the next page's cursor.
+    pub fn cursor(self, contest: &Contest) -> Cursor {
+        let value = match self {
+            ContestSort::Deadline => contest.ended_at,
+            ContestSort::Start => contest.started_at,
+            ContestSort::MostLiked => contest.like_count.into(),
+        };
+        Cursor {
+            value,
+            id: contest.contest_id,
+        }
     }
 }
 
@@ -136,7 +670,7 @@ pub struct Post {
 }
 
 impl Post {
-    pub async fn insert(pool: &SqlitePool, post: &Post) -> i64 {
+    pub async fn insert(pool: &SqlitePool, post: &Post) -> Result<i64, sqlx::Error> {
         sqlx::query(
             r#"
             INSERT INTO posts (user_id, contest_id, title, content, max, ppl, desired_field, created_at, ended_at, like_count)
@@ -155,31 +689,192 @@ impl Post {
         .bind(post.like_count)
         .execute(pool)
         .await
-        .unwrap()
-        .last_insert_rowid()
+        .map(|result| result.last_insert_rowid())
     }
 
-    pub async fn find_all(pool: &SqlitePool) -> Vec<Post> {
-        sqlx::query_as::<_, Post>("SELECT * FROM posts")
-            .fetch_all(pool)
-            .await
-            .unwrap()
+    /// Returns up to `limit` posts matching `filter` in `sort` order, starting after `after`.
+    pub async fn search(
+        pool: &SqlitePool,
+        filter: &PostFilter,
+        sort: PostSort,
+        after: Option<Cursor>,
+        limit: i64,
+    ) -> Result<Vec<Post>, sqlx::Error> {
+        let mut query = QueryBuilder::new("SELECT * FROM posts WHERE 1 = 1");
+        filter.push_conditions(&mut query);
+        push_page(
+            &mut query,
+            sort.column(),
+            "post_id",
+            sort.descending(),
+            after,
+            limit,
+        );
+        query.build_query_as().fetch_all(pool).await
+    }
+
+    /// Counts every post matching `filter`, ignoring pagination.
+    pub async fn count(pool: &SqlitePool, filter: &PostFilter) -> Result<i64, sqlx::Error> {
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM posts WHERE 1 = 1");
+        filter.push_conditions(&mut query);
+        query.build_query_scalar().fetch_one(pool).await
     }
 
-    pub async fn find_by_contest_id(pool: &SqlitePool, contest_id: i32) -> Vec<Post> {
+    pub async fn find_by_contest_id(
+        pool: &SqlitePool,
+        contest_id: i32,
+    ) -> Result<Vec<Post>, sqlx::Error> {
         sqlx::query_as("SELECT * FROM posts WHERE contest_id = ?")
             .bind(contest_id)
             .fetch_all(pool)
             .await
-            .unwrap()
     }
 
-    pub async fn find_by_id(pool: &SqlitePool, post_id: i32) -> Option<Post> {
+    pub async fn find_by_id(pool: &SqlitePool, post_id: i32) -> Result<Option<Post>, sqlx::Error> {
         sqlx::query_as::<_, Post>("SELECT * FROM posts WHERE post_id = ?")
             .bind(post_id)
             .fetch_optional(pool)
             .await
-            .unwrap()
+    }
+
+    /// Saves the editable fields of `post`.
+    pub async fn update(pool: &SqlitePool, post: &Post) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE posts
+            SET title = ?, content = ?, max = ?, desired_field = ?, ended_at = ?
+            WHERE post_id = ?
+            "#,
+        )
+        .bind(&post.title)
+        .bind(&post.content)
+        .bind(post.max)
+        .bind(post.desired_field)
+        .bind(post.ended_at)
+        .bind(post.post_id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Deletes the post with its comments, applications, attachments, likes and bookmarks. A team
+    /// formed from it stays.
+    pub async fn delete(pool: &SqlitePool, post_id: i32) -> Result<(), sqlx::Error> {
+        cascade(pool, &POST_CASCADE, Some(post_id)).await
+    }
+
+    /// Deletes every post, cleaning up after each as [`Post::delete`] does.
+    pub async fn delete_all(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        cascade(pool, &POST_CASCADE, None).await
+    }
+}
+
+/// What deleting posts takes with it, ending with the posts themselves. Each statement is
+/// completed by [`cascade`] with a condition on the post id.
+const POST_CASCADE: [&str; 7] = [
+    "UPDATE teams SET post_id = NULL WHERE post_id",
+    "DELETE FROM likes WHERE item_kind = 'post' AND item_id",
+    "DELETE FROM bookmarks WHERE item_kind = 'post' AND item_id",
+    "DELETE FROM applications WHERE post_id",
+    "DELETE FROM attachments WHERE post_id",
+    "DELETE FROM comments WHERE post_id",
+    "DELETE FROM posts WHERE post_id",
+];
+
+/// Conditions for [`Post::search`]. Unset fields match every post.
+#[derive(Default)]
+pub struct PostFilter {
+    pub contest_id: Option<i32>,
+    pub desired_field: Option<i32>,
+    pub user_id: Option<i32>,
+    /// `Some(true)` keeps posts still recruiting at `now`: not past `ended_at` and not full.
+    /// `Some(false)` keeps the rest.
+    pub open: Option<bool>,
+    /// Inclusive lower bound on `created_at`.
+    pub created_after: Option<i64>,
+    /// Exclusive upper bound on `created_at`.
+    pub created_before: Option<i64>,
+    pub now: i64,
+}
+
+impl PostFilter {
+    fn push_conditions(&self, query: &mut QueryBuilder<'_, Sqlite>) {
+        if let Some(contest_id) = self.contest_id {
+            query.push(" AND contest_id = ").push_bind(contest_id);
+        }
+        if let Some(desired_field) = self.desired_field {
+            query.push(" AND desired_field = ").push_bind(desired_field);
+        }
+        if let Some(user_id) = self.user_id {
+            query.push(" AND user_id = ").push_bind(user_id);
+        }
+        match self.open {
+            Some(true) => {
+                query
+                    .push(" AND ended_at > ")
+                    .push_bind(self.now)
+                    .push(" AND ppl < max");
+            }
+            Some(false) => {
+                query
+                    .push(" AND (ended_at <= ")
+                    .push_bind(self.now)
+                    .push(" OR ppl >= max)");
+            }
+            None => {}
+        }
+        if let Some(created_after) = self.created_after {
+            query.push(" AND created_at >= ").push_bind(created_after);
+        }
+        if let Some(created_before) = self.created_before {
+            query.push(" AND created_at < ").push_bind(created_before);
+        }
+    }
+}
+
+/// Order of a post listing. Ties are broken by `post_id` in the same direction.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PostSort {
+    #[default]
+    Newest,
+    /// Soonest `ended_at` first.
+    Deadline,
+    MostLiked,
+}
+
+impl PostSort {
+    pub fn name(self) -> &'static str {
+        match self {
+            PostSort::Newest => "newest",
+            PostSort::Deadline => "deadline",
+            PostSort::MostLiked => "mostLiked",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            PostSort::Newest => "created_at",
+            PostSort::Deadline => "ended_at",
+            PostSort::MostLiked => "like_count",
+        }
+    }
+
+    fn descending(self) -> bool {
+        !matches!(self, PostSort::Deadline)
+    }
+
+    /// Position of `post` in this order, for building the next page's cursor.
+    pub fn cursor(self, post: &Post) -> Cursor {
+        let value = match self {
+            PostSort::Newest => post.created_at,
+            PostSort::Deadline => post.ended_at,
+            PostSort::MostLiked => post.like_count.into(),
+        };
+        Cursor {
+            value,
+            id: post.post_id,
+        }
     }
 }
 
@@ -193,6 +888,8 @@ pub struct Comment {
     pub created_at: i64,
     pub edited_at: Option<i64>,
     pub parent: Option<i32>,
+    /// Set on a deleted comment that is kept as a tombstone because it still has replies.
+    pub is_deleted: bool,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, FromRow)]
@@ -200,16 +897,18 @@ pub struct Comment {
 pub struct CommentWithUser {
     pub comment_id: i32,
     pub post_id: i32,
-    pub user_id: i32,
-    pub nickname: String,
+    /// `None` on tombstones, which no longer show who wrote them.
+    pub user_id: Option<i32>,
+    pub nickname: Option<String>,
     pub content: String,
     pub created_at: i64,
     pub edited_at: Option<i64>,
     pub parent: Option<i32>,
+    pub is_deleted: bool,
 }
 
 impl Comment {
-    pub async fn insert(pool: &SqlitePool, comment: &Comment) -> i64 {
+    pub async fn insert(pool: &SqlitePool, comment: &Comment) -> Result<i64, sqlx::Error> {
         sqlx::query(
             r#"
             INSERT INTO comments (post_id, user_id, content, created_at, edited_at, parent)
@@ -224,14 +923,20 @@ impl Comment {
         .bind(comment.parent)
         .execute(pool)
         .await
-        .unwrap()
-        .last_insert_rowid()
+        .map(|result| result.last_insert_rowid())
     }
 
-    pub async fn find_by_post_id(pool: &SqlitePool, post_id: i32) -> Vec<CommentWithUser> {
+    pub async fn find_by_post_id(
+        pool: &SqlitePool,
+        post_id: i32,
+    ) -> Result<Vec<CommentWithUser>, sqlx::Error> {
         sqlx::query_as(
             r#"
-            SELECT comments.comment_id, comments.post_id, comments.user_id, users.nickname, comments.content, comments.created_at, comments.edited_at, comments.parent
+            SELECT
+                comments.comment_id, comments.post_id,
+                CASE WHEN comments.is_deleted THEN NULL ELSE comments.user_id END AS user_id,
+                CASE WHEN comments.is_deleted THEN NULL ELSE users.nickname END AS nickname,
+                comments.content, comments.created_at, comments.edited_at, comments.parent, comments.is_deleted
             FROM comments
             JOIN users ON comments.user_id = users.id
             WHERE comments.post_id = ?
@@ -240,6 +945,924 @@ impl Comment {
         .bind(post_id)
         .fetch_all(pool)
         .await
-        .unwrap()
     }
+
+    pub async fn find_by_id(
+        pool: &SqlitePool,
+        comment_id: i32,
+    ) -> Result<Option<Comment>, sqlx::Error> {
+        sqlx::query_as::<_, Comment>("SELECT * FROM comments WHERE comment_id = ?")
+            .bind(comment_id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    pub async fn update_content(
+        pool: &SqlitePool,
+        comment_id: i32,
+        content: &str,
+        edited_at: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE comments SET content = ?, edited_at = ? WHERE comment_id = ?")
+            .bind(content)
+            .bind(edited_at)
+            .bind(comment_id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes the comment. A comment with replies becomes a tombstone instead so the thread
+    /// below it stays reachable, and tombstones left without replies are removed.
+    pub async fn delete(pool: &SqlitePool, comment: &Comment) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        let has_replies = |comment_id: i32| {
+            sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM comments WHERE parent = ?)")
+                .bind(comment_id)
+        };
+
+        if has_replies(comment.comment_id).fetch_one(&mut *tx).await? {
+            sqlx::query("UPDATE comments SET is_deleted = TRUE, content = '' WHERE comment_id = ?")
+                .bind(comment.comment_id)
+                .execute(&mut *tx)
+                .await?;
+            return tx.commit().await;
+        }
+
+        sqlx::query("DELETE FROM comments WHERE comment_id = ?")
+            .bind(comment.comment_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let mut parent = comment.parent;
+        while let Some(parent_id) = parent {
+            let Some(row) =
+                sqlx::query_as::<_, Comment>("SELECT * FROM comments WHERE comment_id = ?")
+                    .bind(parent_id)
+                    .fetch_optional(&mut *tx)
+                    .await?
+            else {
+                break;
+            };
+            if !row.is_deleted || has_replies(row.comment_id).fetch_one(&mut *tx).await? {
+                break;
+            }
+            sqlx::query("DELETE FROM comments WHERE comment_id = ?")
+                .bind(row.comment_id)
+                .execute(&mut *tx)
+                .await?;
+            parent = row.parent;
+        }
+
+        tx.commit().await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum ApplicationStatus {
+    #[default]
+    Pending,
+    Accepted,
+    Rejected,
+    Withdrawn,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Application {
+    pub application_id: i32,
+    pub post_id: i32,
+    pub user_id: i32,
+    pub message: String,
+    pub status: ApplicationStatus,
+    pub created_at: i64,
+    pub decided_at: Option<i64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplicationWithUser {
+    pub application_id: i32,
+    pub post_id: i32,
+    pub user_id: i32,
+    pub nickname: String,
+    pub message: String,
+    pub status: ApplicationStatus,
+    pub created_at: i64,
+    pub decided_at: Option<i64>,
+}
+
+/// Outcome of [`Application::accept`].
+#[derive(Debug, PartialEq)]
+pub enum AcceptResult {
+    Accepted,
+    /// The post already has `max` members.
+    PostFull,
+    /// The application was decided or withdrawn in the meantime.
+    NotPending,
+}
+
+impl Application {
+    pub async fn insert(pool: &SqlitePool, application: &Application) -> Result<i64, sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO applications (post_id, user_id, message, status, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(application.post_id)
+        .bind(application.user_id)
+        .bind(&application.message)
+        .bind(application.status)
+        .bind(application.created_at)
+        .execute(pool)
+        .await
+        .map(|result| result.last_insert_rowid())
+    }
+
+    pub async fn find_by_id(
+        pool: &SqlitePool,
+        application_id: i32,
+    ) -> Result<Option<Application>, sqlx::Error> {
+        sqlx::query_as::<_, Application>("SELECT * FROM applications WHERE application_id = ?")
+            .bind(application_id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    /// Applications to `post_id`, oldest first.
+    pub async fn find_by_post_id(
+        pool: &SqlitePool,
+        post_id: i32,
+    ) -> Result<Vec<ApplicationWithUser>, sqlx::Error> {
+        sqlx::query_as::<_, ApplicationWithUser>(
+            r#"
+            SELECT a.application_id, a.post_id, a.user_id, u.nickname, a.message, a.status, a.created_at, a.decided_at
+            FROM applications a
+            JOIN users u ON a.user_id = u.id
+            WHERE a.post_id = ?
+            ORDER BY a.application_id
+            "#,
+        )
+        .bind(post_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Moves a pending application to `status`. Returns `false` if it was no longer pending.
+    pub async fn decide(
+        pool: &SqlitePool,
+        application_id: i32,
+        status: ApplicationStatus,
+        decided_at: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE applications SET status = ?, decided_at = ? WHERE application_id = ? AND status = 'pending'",
+        )
+        .bind(status)
+        .bind(decided_at)
+        .bind(application_id)
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Accepts a pending application and takes a seat on its post in one transaction. The
+    /// accept that fills the post also rejects every other pending application, closing it.
+    /// If a team was already formed from the post, the applicant joins it.
+    pub async fn accept(
+        pool: &SqlitePool,
+        application: &Application,
+        decided_at: i64,
+    ) -> Result<AcceptResult, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        let seated = sqlx::query("UPDATE posts SET ppl = ppl + 1 WHERE post_id = ? AND ppl < max")
+            .bind(application.post_id)
+            .execute(&mut *tx)
+            .await?;
+        if seated.rows_affected() == 0 {
+            return Ok(AcceptResult::PostFull);
+        }
+
+        let accepted = sqlx::query(
+            "UPDATE applications SET status = 'accepted', decided_at = ? WHERE application_id = ? AND status = 'pending'",
+        )
+        .bind(decided_at)
+        .bind(application.application_id)
+        .execute(&mut *tx)
+        .await?;
+        if accepted.rows_affected() == 0 {
+            return Ok(AcceptResult::NotPending);
+        }
+
+        // Someone accepted after the team was formed joins it straight away.
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO team_members (team_id, user_id, role, joined_at)
+            SELECT team_id, ?, 'member', ? FROM teams WHERE post_id = ?
+            "#,
+        )
+        .bind(application.user_id)
+        .bind(decided_at)
+        .bind(application.post_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE applications SET status = 'rejected', decided_at = ?
+            WHERE post_id = ? AND status = 'pending'
+              AND (SELECT ppl >= max FROM posts WHERE post_id = ?)
+            "#,
+        )
+        .bind(decided_at)
+        .bind(application.post_id)
+        .bind(application.post_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(AcceptResult::Accepted)
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Team {
+    pub team_id: i32,
+    pub post_id: Option<i32>,
+    pub contest_id: Option<i32>,
+    pub name: String,
+    pub created_at: i64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum TeamRole {
+    Leader,
+    #[default]
+    Member,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamMember {
+    pub user_id: i32,
+    pub nickname: String,
+    pub role: TeamRole,
+    pub joined_at: i64,
+}
+
+impl Team {
+    /// Creates a team from `team.post_id` led by `leader_id`. Everyone whose application to the
+    /// post was accepted joins as a member.
+    pub async fn insert(
+        pool: &SqlitePool,
+        team: &Team,
+        leader_id: i32,
+    ) -> Result<i64, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        let team_id = sqlx::query(
+            "INSERT INTO teams (post_id, contest_id, name, created_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(team.post_id)
+        .bind(team.contest_id)
+        .bind(&team.name)
+        .bind(team.created_at)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+        sqlx::query(
+            "INSERT INTO team_members (team_id, user_id, role, joined_at) VALUES (?, ?, 'leader', ?)",
+        )
+        .bind(team_id)
+        .bind(leader_id)
+        .bind(team.created_at)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO team_members (team_id, user_id, role, joined_at)
+            SELECT ?, user_id, 'member', ? FROM applications
+            WHERE post_id = ? AND status = 'accepted' AND user_id != ?
+            "#,
+        )
+        .bind(team_id)
+        .bind(team.created_at)
+        .bind(team.post_id)
+        .bind(leader_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(team_id)
+    }
+
+    pub async fn find_by_id(pool: &SqlitePool, team_id: i32) -> Result<Option<Team>, sqlx::Error> {
+        sqlx::query_as::<_, Team>("SELECT * FROM teams WHERE team_id = ?")
+            .bind(team_id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    /// Teams `user_id` belongs to, most recently formed first.
+    pub async fn find_by_user_id(
+        pool: &SqlitePool,
+        user_id: i32,
+    ) -> Result<Vec<Team>, sqlx::Error> {
+        sqlx::query_as::<_, Team>(
+            r#"
+            SELECT t.* FROM teams t
+            JOIN team_members m ON m.team_id = t.team_id
+            WHERE m.user_id = ?
+            ORDER BY t.team_id DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Members of `team_id`, leader first and then in joining order.
+    pub async fn members(pool: &SqlitePool, team_id: i32) -> Result<Vec<TeamMember>, sqlx::Error> {
+        sqlx::query_as::<_, TeamMember>(
+            r#"
+            SELECT m.user_id, u.nickname, m.role, m.joined_at
+            FROM team_members m
+            JOIN users u ON m.user_id = u.id
+            WHERE m.team_id = ?
+            ORDER BY m.role = 'leader' DESC, m.joined_at, m.user_id
+            "#,
+        )
+        .bind(team_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Removes `user_id` from the team. Returns `false` if they were not a member.
+    pub async fn remove_member(
+        pool: &SqlitePool,
+        team_id: i32,
+        user_id: i32,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM team_members WHERE team_id = ? AND user_id = ?")
+            .bind(team_id)
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Makes member `to` the leader and demotes `from`. Returns `false`, changing nothing, if
+    /// `from` is not the leader or `to` is not a member.
+    pub async fn transfer_leadership(
+        pool: &SqlitePool,
+        team_id: i32,
+        from: i32,
+        to: i32,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        let demoted = sqlx::query(
+            "UPDATE team_members SET role = 'member' WHERE team_id = ? AND user_id = ? AND role = 'leader'",
+        )
+        .bind(team_id)
+        .bind(from)
+        .execute(&mut *tx)
+        .await?;
+        let promoted = sqlx::query(
+            "UPDATE team_members SET role = 'leader' WHERE team_id = ? AND user_id = ? AND role = 'member'",
+        )
+        .bind(team_id)
+        .bind(to)
+        .execute(&mut *tx)
+        .await?;
+        if demoted.rows_affected() == 0 || promoted.rows_affected() == 0 {
+            return Ok(false);
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Disbands the team, removing every member.
+    pub async fn delete(pool: &SqlitePool, team_id: i32) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM team_members WHERE team_id = ?")
+            .bind(team_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM teams WHERE team_id = ?")
+            .bind(team_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+}
+
+/// Content that users can like and bookmark.
+#[derive(Clone, Copy, Debug, PartialEq, sqlx::Type)]
+#[sqlx(rename_all = "lowercase")]
+pub enum ItemKind {
+    Contest,
+    Post,
+}
+
+impl ItemKind {
+    /// Table holding items of this kind and its primary key column.
+    fn table(self) -> (&'static str, &'static str) {
+        match self {
+            ItemKind::Contest => ("contests", "contest_id"),
+            ItemKind::Post => ("posts", "post_id"),
+        }
+    }
+}
+
+pub struct Like;
+
+impl Like {
+    /// Likes or unlikes an item for `user_id` and keeps its `like_count` in step, both in one
+    /// transaction. Repeating the current state changes nothing. Returns the item's new
+    /// `like_count`, or `None` if the item does not exist.
+    pub async fn set(
+        pool: &SqlitePool,
+        user_id: i32,
+        kind: ItemKind,
+        item_id: i32,
+        liked: bool,
+        now: i64,
+    ) -> Result<Option<i32>, sqlx::Error> {
+        let (table, id_column) = kind.table();
+        // Writing first makes the transaction take SQLite's write lock up front. Starting with a
+        // read would let a concurrent like commit in between and fail this one with SQLITE_BUSY.
+        let mut tx = pool.begin().await?;
+
+        let changed = if liked {
+            sqlx::query(
+                "INSERT OR IGNORE INTO likes (user_id, item_kind, item_id, created_at) VALUES (?, ?, ?, ?)",
+            )
+            .bind(user_id)
+            .bind(kind)
+            .bind(item_id)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?
+        } else {
+            sqlx::query("DELETE FROM likes WHERE user_id = ? AND item_kind = ? AND item_id = ?")
+                .bind(user_id)
+                .bind(kind)
+                .bind(item_id)
+                .execute(&mut *tx)
+                .await?
+        };
+
+        let delta = match (changed.rows_affected(), liked) {
+            (0, _) => 0,
+            (_, true) => 1,
+            (_, false) => -1,
+        };
+        let like_count = sqlx::query_scalar(&format!(
+            "UPDATE {table} SET like_count = like_count + ? WHERE {id_column} = ? RETURNING like_count"
+        ))
+        .bind(delta)
+        .bind(item_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        // Dropping the transaction rolls back a like recorded for a missing item.
+        if like_count.is_some() {
+            tx.commit().await?;
+        }
+        Ok(like_count)
+    }
+
+    /// Which of `item_ids` `user_id` has liked.
+    pub async fn find_liked(
+        pool: &SqlitePool,
+        user_id: i32,
+        kind: ItemKind,
+        item_ids: &[i32],
+    ) -> Result<HashSet<i32>, sqlx::Error> {
+        find_marked(pool, "likes", user_id, kind, item_ids).await
+    }
+}
+
+pub struct Bookmark;
+
+impl Bookmark {
+    /// Saves or unsaves an item for `user_id`. Repeating the current state changes nothing.
+    /// Returns `false` if the item does not exist.
+    pub async fn set(
+        pool: &SqlitePool,
+        user_id: i32,
+        kind: ItemKind,
+        item_id: i32,
+        bookmarked: bool,
+        now: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let (table, id_column) = kind.table();
+        // Written before checking the item, for the same reason as in `Like::set`.
+        let mut tx = pool.begin().await?;
+
+        if bookmarked {
+            sqlx::query(
+                "INSERT OR IGNORE INTO bookmarks (user_id, item_kind, item_id, created_at) VALUES (?, ?, ?, ?)",
+            )
+            .bind(user_id)
+            .bind(kind)
+            .bind(item_id)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        } else {
+            sqlx::query(
+                "DELETE FROM bookmarks WHERE user_id = ? AND item_kind = ? AND item_id = ?",
+            )
+            .bind(user_id)
+            .bind(kind)
+            .bind(item_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let exists = sqlx::query_scalar::<_, bool>(&format!(
+            "SELECT EXISTS (SELECT 1 FROM {table} WHERE {id_column} = ?)"
+        ))
+        .bind(item_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if exists {
+            tx.commit().await?;
+        }
+        Ok(exists)
+    }
+
+    /// Which of `item_ids` `user_id` has bookmarked.
+    pub async fn find_bookmarked(
+        pool: &SqlitePool,
+        user_id: i32,
+        kind: ItemKind,
+        item_ids: &[i32],
+    ) -> Result<HashSet<i32>, sqlx::Error> {
+        find_marked(pool, "bookmarks", user_id, kind, item_ids).await
+    }
+
+    /// Contests `user_id` has bookmarked, most recently saved first.
+    pub async fn find_contests(
+        pool: &SqlitePool,
+        user_id: i32,
+    ) -> Result<Vec<Contest>, sqlx::Error> {
+        sqlx::query_as::<_, Contest>(
+            r#"
+            SELECT c.* FROM contests c
+            JOIN bookmarks b ON b.item_kind = 'contest' AND b.item_id = c.contest_id
+            WHERE b.user_id = ?
+            ORDER BY b.created_at DESC, c.contest_id DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Posts `user_id` has bookmarked, most recently saved first.
+    pub async fn find_posts(pool: &SqlitePool, user_id: i32) -> Result<Vec<Post>, sqlx::Error> {
+        sqlx::query_as::<_, Post>(
+            r#"
+            SELECT p.* FROM posts p
+            JOIN bookmarks b ON b.item_kind = 'post' AND b.item_id = p.post_id
+            WHERE b.user_id = ?
+            ORDER BY b.created_at DESC, p.post_id DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    }
+}
+
+/// Which of `item_ids` have a row for `user_id` in `table`, which is `likes` or `bookmarks`.
+async fn find_marked(
+    pool: &SqlitePool,
+    table: &str,
+    user_id: i32,
+    kind: ItemKind,
+    item_ids: &[i32],
+) -> Result<HashSet<i32>, sqlx::Error> {
+    if item_ids.is_empty() {
+        return Ok(HashSet::new());
+    }
+    let mut query = QueryBuilder::new(format!("SELECT item_id FROM {table} WHERE user_id = "));
+    query
+        .push_bind(user_id)
+        .push(" AND item_kind = ")
+        .push_bind(kind)
+        .push(" AND item_id IN (");
+    let mut ids = query.separated(", ");
+    for &item_id in item_ids {
+        ids.push_bind(item_id);
+    }
+    ids.push_unseparated(")");
+
+    let marked = query.build_query_scalar().fetch_all(pool).await?;
+    Ok(marked.into_iter().collect())
+}
+
+#[derive(Clone, Debug, Default, FromRow)]
+pub struct Session {
+    pub id: i32,
+    pub user_id: i32,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: i64,
+    pub last_seen_at: i64,
+    pub revoked: bool,
+}
+
+impl Session {
+    pub async fn insert(pool: &SqlitePool, session: &Session) -> Result<i64, sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO sessions (user_id, user_agent, ip, created_at, last_seen_at, revoked)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(session.user_id)
+        .bind(&session.user_agent)
+        .bind(&session.ip)
+        .bind(session.created_at)
+        .bind(session.last_seen_at)
+        .bind(session.revoked)
+        .execute(pool)
+        .await
+        .map(|result| result.last_insert_rowid())
+    }
+
+    pub async fn find_by_id(pool: &SqlitePool, id: i32) -> Result<Option<Session>, sqlx::Error> {
+        sqlx::query_as::<_, Session>("SELECT * FROM sessions WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    pub async fn find_active_by_user_id(
+        pool: &SqlitePool,
+        user_id: i32,
+    ) -> Result<Vec<Session>, sqlx::Error> {
+        sqlx::query_as::<_, Session>(
+            "SELECT * FROM sessions WHERE user_id = ? AND revoked = FALSE ORDER BY last_seen_at DESC",
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn touch(pool: &SqlitePool, id: i32, last_seen_at: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE sessions SET last_seen_at = ? WHERE id = ?")
+            .bind(last_seen_at)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn revoke(pool: &SqlitePool, id: i32) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE sessions SET revoked = TRUE WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Revokes every session of `user_id` except `keep`.
+    pub async fn revoke_others(
+        pool: &SqlitePool,
+        user_id: i32,
+        keep: i32,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE sessions SET revoked = TRUE WHERE user_id = ? AND id != ?")
+            .bind(user_id)
+            .bind(keep)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, FromRow)]
+pub struct RefreshToken {
+    pub id: i32,
+    pub session_id: i32,
+    pub token_hash: String,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub rotated_at: Option<i64>,
+}
+
+impl RefreshToken {
+    pub async fn insert(pool: &SqlitePool, token: &RefreshToken) -> Result<i64, sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO refresh_tokens (session_id, token_hash, created_at, expires_at, rotated_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(token.session_id)
+        .bind(&token.token_hash)
+        .bind(token.created_at)
+        .bind(token.expires_at)
+        .bind(token.rotated_at)
+        .execute(pool)
+        .await
+        .map(|result| result.last_insert_rowid())
+    }
+
+    pub async fn find_by_hash(
+        pool: &SqlitePool,
+        token_hash: &str,
+    ) -> Result<Option<RefreshToken>, sqlx::Error> {
+        sqlx::query_as::<_, RefreshToken>("SELECT * FROM refresh_tokens WHERE token_hash = ?")
+            .bind(token_hash)
+            .fetch_optional(pool)
+            .await
+    }
+
+    /// Marks the token as used. Returns `false` if it had already been rotated, which means the
+    /// token was replayed.
+    pub async fn mark_rotated(
+        pool: &SqlitePool,
+        id: i32,
+        rotated_at: i64,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query("UPDATE refresh_tokens SET rotated_at = ? WHERE id = ? AND rotated_at IS NULL")
+            .bind(rotated_at)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected() == 1)
+    }
+}
+
+/// A table covered by full-text search.
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchTarget {
+    Contests,
+    Posts,
+    Comments,
+}
+
+/// A full-text search hit. Contests match on title and prize, posts on title and content, and
+/// comments on content, which is returned as `body` with an empty `title`.
+#[derive(Debug, FromRow)]
+pub struct SearchRow {
+    pub id: i32,
+    /// The post a comment belongs to, `None` for contests and posts.
+    pub post_id: Option<i32>,
+    pub title: String,
+    pub body: String,
+    /// Higher is more relevant. Zero when the query had no term long enough to rank with.
+    pub score: f64,
+}
+
+impl SearchTarget {
+    /// Returns up to `limit` rows containing every term of `terms`, best match first.
+    ///
+    /// The trigram index only matches terms of three or more characters, so shorter terms
+    /// (common for two-syllable Korean words) are checked with `LIKE` instead. When no term
+    /// is long enough to use the index, rows are ranked newest first.
+    pub async fn search(
+        self,
+        pool: &SqlitePool,
+        terms: &[String],
+        limit: i64,
+    ) -> Result<Vec<SearchRow>, sqlx::Error> {
+        let (fts, select, join, columns): (_, _, _, &[_]) = match self {
+            SearchTarget::Contests => (
+                "contests_fts",
+                "c.contest_id AS id, NULL AS post_id, c.title AS title, c.prize AS body",
+                "contests c ON c.contest_id = contests_fts.rowid",
+                &["c.title", "c.prize"],
+            ),
+            SearchTarget::Posts => (
+                "posts_fts",
+                "p.post_id AS id, NULL AS post_id, p.title AS title, p.content AS body",
+                "posts p ON p.post_id = posts_fts.rowid",
+                &["p.title", "p.content"],
+            ),
+            SearchTarget::Comments => (
+                "comments_fts",
+                "c.comment_id AS id, c.post_id AS post_id, '' AS title, c.content AS body",
+                "comments c ON c.comment_id = comments_fts.rowid AND NOT c.is_deleted",
+                &["c.content"],
+            ),
+        };
+
+        let (long, short): (Vec<_>, Vec<_>) =
+            terms.iter().partition(|term| term.chars().count() >= 3);
+        let score = if long.is_empty() {
+            "0.0".to_string()
+        } else {
+            format!("-bm25({fts})")
+        };
+
+        let mut query = QueryBuilder::new(format!(
+            "SELECT {select}, {score} AS score FROM {fts} JOIN {join} WHERE 1 = 1"
+        ));
+        if !long.is_empty() {
+            // Each term becomes a quoted phrase, so FTS5 operators in user input are literal.
+            let expression = long
+                .iter()
+                .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+                .collect::<Vec<_>>()
+                .join(" ");
+            query
+                .push(format!(" AND {fts} MATCH "))
+                .push_bind(expression);
+        }
+        for term in short {
+            let pattern = like_pattern(term);
+            query.push(" AND (");
+            for (i, column) in columns.iter().enumerate() {
+                if i > 0 {
+                    query.push(" OR ");
+                }
+                query
+                    .push(format!("{column} LIKE "))
+                    .push_bind(pattern.clone())
+                    .push(r" ESCAPE '\'");
+            }
+            query.push(")");
+        }
+        query
+            .push(format!(" ORDER BY score DESC, {fts}.rowid DESC LIMIT "))
+            .push_bind(limit);
+
+        query.build_query_as().fetch_all(pool).await
+    }
+}
+
+/// Runs `statements` in one transaction, each restricted to rows referencing `id`, or to every
+/// referencing row when `id` is `None`. The statements must end in the id column.
+async fn cascade(
+    pool: &SqlitePool,
+    statements: &[&str],
+    id: Option<i32>,
+) -> Result<(), sqlx::Error> {
+    let condition = if id.is_some() { "= ?" } else { "IS NOT NULL" };
+    let mut tx = pool.begin().await?;
+    for statement in statements {
+        let sql = format!("{statement} {condition}");
+        let mut query = sqlx::query(&sql);
+        if let Some(id) = id {
+            query = query.bind(id);
+        }
+        query.execute(&mut *tx).await?;
+    }
+    tx.commit().await
+}
+
+/// Appends the keyset condition for rows after `after`, then the ordering and `LIMIT`.
+/// `column` and `id_column` must be trusted identifiers, never user input.
+fn push_page(
+    query: &mut QueryBuilder<'_, Sqlite>,
+    column: &str,
+    id_column: &str,
+    descending: bool,
+    after: Option<Cursor>,
+    limit: i64,
+) {
+    let (cmp, order) = if descending {
+        ("<", "DESC")
+    } else {
+        (">", "ASC")
+    };
+    if let Some(after) = after {
+        query
+            .push(format!(" AND ({column} {cmp} "))
+            .push_bind(after.value)
+            .push(format!(" OR ({column} = "))
+            .push_bind(after.value)
+            .push(format!(" AND {id_column} {cmp} "))
+            .push_bind(after.id)
+            .push("))");
+    }
+    query
+        .push(format!(
+            " ORDER BY {column} {order}, {id_column} {order} LIMIT "
+        ))
+        .push_bind(limit);
+}
+
+/// A `LIKE` pattern matching `text` anywhere, with `\` as the escape character.
+fn like_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
 }
@@ -0,0 +1,74 @@
+use serde::Serialize;
+
+use crate::error::AppError;
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+/// One page of a keyset-paginated listing.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Pass back as `cursor` to get the next page. `None` on the last page.
+    pub next_cursor: Option<String>,
+    /// Number of matching rows across all pages, only counted when asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+}
+
+/// Sort key and id of the last row on a page. Rows strictly after it form the next page.
+#[derive(Clone, Copy, Debug)]
+pub struct Cursor {
+    pub value: i64,
+    pub id: i32,
+}
+
+impl Cursor {
+    /// `sort` is baked into the cursor so one from a differently sorted listing is rejected.
+    pub fn encode(&self, sort: &str) -> String {
+        format!("{sort}.{}.{}", self.value, self.id)
+    }
+
+    pub fn decode(cursor: &str, sort: &str) -> Result<Cursor, AppError> {
+        let invalid = || AppError::unprocessable("Invalid cursor");
+
+        let mut parts = cursor.split('.');
+        if parts.next() != Some(sort) {
+            return Err(invalid());
+        }
+        let value = parts.next().and_then(|value| value.parse().ok());
+        let id = parts.next().and_then(|id| id.parse().ok());
+        match (value, id, parts.next()) {
+            (Some(value), Some(id), None) => Ok(Cursor { value, id }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Clamps a requested page size to `1..=100`, defaulting to 20.
+pub fn page_limit(limit: Option<i64>) -> i64 {
+    limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+}
+
+/// Builds a page from up to `limit + 1` fetched rows. The extra row only signals that another
+/// page exists, and the cursor is taken from the last row that is kept.
+pub fn into_page<T>(
+    mut rows: Vec<T>,
+    limit: i64,
+    sort: &str,
+    cursor: impl Fn(&T) -> Cursor,
+    total: Option<i64>,
+) -> Page<T> {
+    let has_more = rows.len() as i64 > limit;
+    rows.truncate(limit as usize);
+    let next_cursor = match rows.last() {
+        Some(last) if has_more => Some(cursor(last).encode(sort)),
+        _ => None,
+    };
+    Page {
+        items: rows,
+        next_cursor,
+        total,
+    }
+}
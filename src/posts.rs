@@ -1,34 +1,137 @@
-use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
-    Json,
-};
+use axum::{extract::State, http::StatusCode};
 use serde::{Deserialize, Serialize};
 
-use crate::{auth::Auth, models::Post, utils::now, AppState};
+use crate::{
+    auth::{ensure_owner_or_manager, Auth, MaybeAuth, RequireManager},
+    error::AppError,
+    extract::{Json, Path, Query},
+    jwt::Claims,
+    models::{Bookmark, ItemKind, Like, Post, PostFilter, PostSort},
+    pagination::{into_page, page_limit, Cursor, Page},
+    users::Validation,
+    utils::now,
+    AppState,
+};
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostStatus {
+    Open,
+    Closed,
+}
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SearchQuery {
     contest: Option<i32>,
+    desired_field: Option<i32>,
+    author: Option<i32>,
+    status: Option<PostStatus>,
+    created_after: Option<i64>,
+    created_before: Option<i64>,
+    #[serde(default)]
+    sort: PostSort,
+    cursor: Option<String>,
+    limit: Option<i64>,
+    #[serde(default)]
+    include_total: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {
+    #[serde(flatten)]
+    post: Post,
+    /// Only present when the request is authenticated, like `bookmarked_by_me`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    liked_by_me: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bookmarked_by_me: Option<bool>,
+}
+
+/// Adds what `viewer` did with each post, if anyone is signed in.
+pub async fn post_responses(
+    state: &AppState,
+    viewer: Option<&Claims>,
+    posts: Vec<Post>,
+) -> Result<Vec<PostResponse>, AppError> {
+    let (liked, bookmarked) = match viewer {
+        Some(claims) => {
+            let ids = posts.iter().map(|post| post.post_id).collect::<Vec<_>>();
+            (
+                Some(Like::find_liked(&state.pool, claims.sub, ItemKind::Post, &ids).await?),
+                Some(
+                    Bookmark::find_bookmarked(&state.pool, claims.sub, ItemKind::Post, &ids)
+                        .await?,
+                ),
+            )
+        }
+        None => (None, None),
+    };
+    Ok(posts
+        .into_iter()
+        .map(|post| PostResponse {
+            liked_by_me: liked.as_ref().map(|liked| liked.contains(&post.post_id)),
+            bookmarked_by_me: bookmarked
+                .as_ref()
+                .map(|bookmarked| bookmarked.contains(&post.post_id)),
+            post,
+        })
+        .collect())
 }
 
 pub async fn list_posts(
     State(state): State<AppState>,
-    query: Query<SearchQuery>,
-) -> impl IntoResponse {
-    if let Some(contest) = query.contest {
-        Json(Post::find_by_contest_id(&state.pool, contest).await)
+    MaybeAuth(viewer): MaybeAuth,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<Page<PostResponse>>, AppError> {
+    let filter = PostFilter {
+        contest_id: query.contest,
+        desired_field: query.desired_field,
+        user_id: query.author,
+        open: query
+            .status
+            .map(|status| matches!(status, PostStatus::Open)),
+        created_after: query.created_after,
+        created_before: query.created_before,
+        now: now(),
+    };
+    let sort = query.sort;
+    let after = query
+        .cursor
+        .map(|cursor| Cursor::decode(&cursor, sort.name()))
+        .transpose()?;
+    let limit = page_limit(query.limit);
+
+    let posts = Post::search(&state.pool, &filter, sort, after, limit + 1).await?;
+    let total = if query.include_total {
+        Some(Post::count(&state.pool, &filter).await?)
     } else {
-        Json(Post::find_all(&state.pool).await)
-    }
+        None
+    };
+    let page = into_page(posts, limit, sort.name(), |post| sort.cursor(post), total);
+    Ok(Json(Page {
+        items: post_responses(&state, viewer.as_ref(), page.items).await?,
+        next_cursor: page.next_cursor,
+        total: page.total,
+    }))
+}
+
+/// Loads `post_id`, or fails with a 404.
+pub async fn find_post(state: &AppState, post_id: i32) -> Result<Post, AppError> {
+    Post::find_by_id(&state.pool, post_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Post not found"))
 }
 
 pub async fn get_post(
     State(state): State<AppState>,
+    MaybeAuth(viewer): MaybeAuth,
     Path(post_id): Path<i32>,
-) -> impl IntoResponse {
-    Json(Post::find_by_id(&state.pool, post_id).await)
+) -> Result<Json<PostResponse>, AppError> {
+    let post = find_post(&state, post_id).await?;
+    let mut posts = post_responses(&state, viewer.as_ref(), vec![post]).await?;
+    Ok(Json(posts.remove(0)))
 }
 
 #[derive(Deserialize)]
@@ -53,7 +156,15 @@ pub async fn create_post(
     State(state): State<AppState>,
     Auth(auth): Auth,
     Json(body): Json<CreatePostBody>,
-) -> impl IntoResponse {
+) -> Result<(StatusCode, Json<CreatePostResponse>), AppError> {
+    let mut validation = Validation::default();
+    if body.max < 1 {
+        validation.fail("max", "must be at least 1".to_string());
+    } else if !(0..=body.max).contains(&body.ppl) {
+        validation.fail("ppl", "must be between 0 and `max`".to_string());
+    }
+    validation.finish()?;
+
     let post_id = Post::insert(
         &state.pool,
         &Post {
@@ -69,13 +180,74 @@ pub async fn create_post(
             ..Default::default()
         },
     )
-    .await as _;
-    (StatusCode::CREATED, Json(CreatePostResponse { post_id }))
+    .await? as _;
+    Ok((StatusCode::CREATED, Json(CreatePostResponse { post_id })))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePostBody {
+    title: Option<String>,
+    content: Option<String>,
+    max: Option<i32>,
+    desired_field: Option<i32>,
+    ended_at: Option<i64>,
+}
+
+pub async fn update_post(
+    State(state): State<AppState>,
+    Path(post_id): Path<i32>,
+    Auth(auth): Auth,
+    Json(body): Json<UpdatePostBody>,
+) -> Result<Json<PostResponse>, AppError> {
+    let mut post = find_post(&state, post_id).await?;
+    ensure_owner_or_manager(&state, &auth, post.user_id).await?;
+
+    if let Some(title) = body.title {
+        post.title = title;
+    }
+    if let Some(content) = body.content {
+        post.content = content;
+    }
+    if let Some(max) = body.max {
+        if max < 1 {
+            return Err(AppError::unprocessable("`max` must be at least 1"));
+        }
+        if max < post.ppl {
+            return Err(AppError::unprocessable(
+                "`max` cannot be lower than the number of members",
+            ));
+        }
+        post.max = max;
+    }
+    if let Some(desired_field) = body.desired_field {
+        post.desired_field = desired_field;
+    }
+    if let Some(ended_at) = body.ended_at {
+        post.ended_at = ended_at;
+    }
+
+    Post::update(&state.pool, &post).await?;
+    let mut posts = post_responses(&state, Some(&auth), vec![post]).await?;
+    Ok(Json(posts.remove(0)))
+}
+
+pub async fn delete_post(
+    State(state): State<AppState>,
+    Path(post_id): Path<i32>,
+    Auth(auth): Auth,
+) -> Result<StatusCode, AppError> {
+    let post = find_post(&state, post_id).await?;
+    ensure_owner_or_manager(&state, &auth, post.user_id).await?;
+
+    Post::delete(&state.pool, post.post_id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
-pub async fn delete_posts(State(state): State<AppState>) -> impl IntoResponse {
-    sqlx::query("DELETE FROM posts")
-        .execute(&state.pool)
-        .await
-        .unwrap();
+pub async fn delete_posts(
+    State(state): State<AppState>,
+    _: RequireManager,
+) -> Result<StatusCode, AppError> {
+    Post::delete_all(&state.pool).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
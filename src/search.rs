@@ -0,0 +1,195 @@
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::AppError,
+    extract::{Json, Query},
+    models::{SearchRow, SearchTarget},
+    pagination::page_limit,
+    AppState,
+};
+
+/// Characters of context kept before the first match in a snippet.
+const SNIPPET_LEAD: usize = 30;
+/// Total characters in a snippet, not counting the ellipses.
+const SNIPPET_LEN: usize = 120;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchQuery {
+    q: String,
+    /// Only search this kind of content. All three are searched when missing.
+    r#type: Option<SearchTarget>,
+    /// Maximum hits per kind.
+    limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContestHit {
+    contest_id: i32,
+    title: String,
+    snippet: String,
+    score: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostHit {
+    post_id: i32,
+    title: String,
+    snippet: String,
+    score: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentHit {
+    comment_id: i32,
+    post_id: i32,
+    snippet: String,
+    score: f64,
+}
+
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResponse {
+    contests: Vec<ContestHit>,
+    posts: Vec<PostHit>,
+    comments: Vec<CommentHit>,
+}
+
+/// Searches contests, posts and comments for rows containing every whitespace-separated term
+/// of `q`. Snippets are HTML-escaped with matches wrapped in `<mark>`.
+pub async fn search(
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<SearchResponse>, AppError> {
+    let terms = query
+        .q
+        .split_whitespace()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if terms.is_empty() {
+        return Err(AppError::unprocessable("`q` must not be empty"));
+    }
+    let limit = page_limit(query.limit);
+    let wanted = |target| query.r#type.is_none_or(|only| only == target);
+
+    let mut response = SearchResponse::default();
+    if wanted(SearchTarget::Contests) {
+        let rows = SearchTarget::Contests
+            .search(&state.pool, &terms, limit)
+            .await?;
+        response.contests = rows
+            .into_iter()
+            .map(|row| ContestHit {
+                contest_id: row.id,
+                snippet: snippet(&row, &terms),
+                title: row.title,
+                score: row.score,
+            })
+            .collect();
+    }
+    if wanted(SearchTarget::Posts) {
+        let rows = SearchTarget::Posts
+            .search(&state.pool, &terms, limit)
+            .await?;
+        response.posts = rows
+            .into_iter()
+            .map(|row| PostHit {
+                post_id: row.id,
+                snippet: snippet(&row, &terms),
+                title: row.title,
+                score: row.score,
+            })
+            .collect();
+    }
+    if wanted(SearchTarget::Comments) {
+        let rows = SearchTarget::Comments
+            .search(&state.pool, &terms, limit)
+            .await?;
+        response.comments = rows
+            .into_iter()
+            .map(|row| CommentHit {
+                comment_id: row.id,
+                post_id: row.post_id.unwrap_or_default(),
+                snippet: snippet(&row, &terms),
+                score: row.score,
+            })
+            .collect();
+    }
+    Ok(Json(response))
+}
+
+/// Cuts a window of `row.body` around the first match, falling back to the title when only
+/// the title matched, and highlights every term inside it.
+///
+/// This is done here rather than with FTS5's `snippet()`, which is unavailable for the `LIKE`
+/// fallback on short terms and counts trigrams rather than characters.
+fn snippet(row: &SearchRow, terms: &[String]) -> String {
+    let lower_terms = terms
+        .iter()
+        .map(|term| term.to_ascii_lowercase())
+        .collect::<Vec<_>>();
+    let body_matches = find_term(&row.body.to_ascii_lowercase(), &lower_terms, 0).is_some();
+    let text = if body_matches || row.title.is_empty() {
+        &row.body
+    } else {
+        &row.title
+    };
+    // ASCII lowercasing keeps byte offsets, so positions in `lower` are valid in `text`.
+    let lower = text.to_ascii_lowercase();
+
+    let first = find_term(&lower, &lower_terms, 0).map_or(0, |(at, _)| at);
+    let chars = text.char_indices().map(|(i, _)| i).collect::<Vec<_>>();
+    let first_char = chars.partition_point(|&i| i < first);
+    let start_char = first_char.saturating_sub(SNIPPET_LEAD);
+    let end_char = (start_char + SNIPPET_LEN).min(chars.len());
+    let start = chars.get(start_char).copied().unwrap_or(text.len());
+    let end = chars.get(end_char).copied().unwrap_or(text.len());
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    let mut at = start;
+    while let Some((found, len)) = find_term(&lower[..end], &lower_terms, at) {
+        escape_into(&mut out, &text[at..found]);
+        out.push_str("<mark>");
+        escape_into(&mut out, &text[found..found + len]);
+        out.push_str("</mark>");
+        at = found + len;
+    }
+    escape_into(&mut out, &text[at..end]);
+    if end < text.len() {
+        out.push('…');
+    }
+    out
+}
+
+/// Byte position and length of the earliest term in `text` at or after `from`, preferring the
+/// longest term when several start at the same place.
+fn find_term(text: &str, terms: &[String], from: usize) -> Option<(usize, usize)> {
+    terms
+        .iter()
+        .filter_map(|term| {
+            text[from..]
+                .find(term.as_str())
+                .map(|i| (from + i, term.len()))
+        })
+        .min_by_key(|&(at, len)| (at, std::cmp::Reverse(len)))
+}
+
+fn escape_into(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use std::{fmt, io, path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, StatusCode, Url};
+use sha2::{Digest, Sha256};
+use time::{macros::format_description, OffsetDateTime};
+
+use crate::config::{S3Config, StorageBackend, StorageConfig};
+
+#[derive(Debug)]
+pub enum StorageError {
+    /// The key is not something this server would have generated.
+    InvalidKey(String),
+    Io(io::Error),
+    Request(reqwest::Error),
+    /// The S3 server answered with an unexpected status.
+    Status(StatusCode, String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageError::InvalidKey(key) => write!(f, "invalid storage key {key:?}"),
+            StorageError::Io(e) => write!(f, "{e}"),
+            StorageError::Request(e) => write!(f, "{e}"),
+            StorageError::Status(status, body) => write!(f, "S3 returned {status}: {body}"),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<io::Error> for StorageError {
+    fn from(error: io::Error) -> StorageError {
+        StorageError::Io(error)
+    }
+}
+
+impl From<reqwest::Error> for StorageError {
+    fn from(error: reqwest::Error) -> StorageError {
+        StorageError::Request(error)
+    }
+}
+
+/// A backend holding uploaded files. Callers only see opaque keys; which files exist and who
+/// references them is tracked in the database (see `files`).
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Stores `data` under `key`, replacing whatever was there.
+    async fn put(&self, key: &str, content_type: &str, data: Vec<u8>) -> Result<(), StorageError>;
+
+    /// Returns `None` if nothing is stored under `key`.
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Deleting a key that does not exist is not an error.
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+/// Builds the backend selected by `config`.
+pub fn from_config(config: &StorageConfig) -> Result<Arc<dyn Storage>, String> {
+    Ok(match config.backend {
+        StorageBackend::Local => Arc::new(LocalStorage::new(config.path.clone())),
+        StorageBackend::S3 => Arc::new(S3Storage::new(&config.s3)?),
+    })
+}
+
+/// Keys are relative paths of `[A-Za-z0-9._-]` segments, which keeps them safe both as file
+/// names and in URLs.
+fn check_key(key: &str) -> Result<(), StorageError> {
+    let valid = !key.is_empty()
+        && key.split('/').all(|segment| {
+            !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b"._-".contains(&b))
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidKey(key.to_string()))
+    }
+}
+
+/// Keeps each file at `{root}/{key}`.
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: PathBuf) -> LocalStorage {
+        LocalStorage { root }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        check_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, _: &str, data: Vec<u8>) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Write then rename, so a reader never sees a half-written file.
+        let partial = path.with_extension(format!("partial-{}", rand::random::<u32>()));
+        tokio::fs::write(&partial, data).await?;
+        if let Err(e) = tokio::fs::rename(&partial, &path).await {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Talks to an S3-compatible server with requests signed by AWS Signature Version 4.
+pub struct S3Storage {
+    client: Client,
+    /// `{endpoint}/{bucket}`, without a trailing slash.
+    base: Url,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+impl S3Storage {
+    pub fn new(config: &S3Config) -> Result<S3Storage, String> {
+        let base = format!(
+            "{}/{}",
+            config.endpoint.trim_end_matches('/'),
+            config.bucket
+        );
+        let base = Url::parse(&base).map_err(|e| format!("storage.s3.endpoint: {e}"))?;
+        if base.host_str().is_none() {
+            return Err(format!(
+                "storage.s3.endpoint {:?} has no host",
+                config.endpoint
+            ));
+        }
+        Ok(S3Storage {
+            client: Client::new(),
+            base,
+            region: config.region.clone(),
+            access_key_id: config.access_key_id.clone(),
+            secret_access_key: config.secret_access_key.clone(),
+        })
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        content_type: Option<&str>,
+        body: Vec<u8>,
+    ) -> Result<reqwest::Response, StorageError> {
+        check_key(key)?;
+        // Keys only contain characters that need no escaping in a path.
+        let url = Url::parse(&format!("{}/{key}", self.base))
+            .map_err(|_| StorageError::InvalidKey(key.to_string()))?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let payload_hash = hex::encode(Sha256::digest(&body));
+        let now = OffsetDateTime::now_utc();
+        let amz_date = now
+            .format(format_description!(
+                "[year][month][day]T[hour][minute][second]Z"
+            ))
+            .expect("formatting a UTC timestamp cannot fail");
+
+        let mut headers = vec![
+            ("host", host.as_str()),
+            ("x-amz-content-sha256", payload_hash.as_str()),
+            ("x-amz-date", amz_date.as_str()),
+        ];
+        if let Some(content_type) = content_type {
+            headers.push(("content-type", content_type));
+        }
+        headers.sort();
+        let authorization = self.authorization(method.as_str(), url.path(), &headers, &amz_date);
+
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("authorization", authorization)
+            .body(body);
+        for (name, value) in headers {
+            if name != "host" {
+                request = request.header(name, value);
+            }
+        }
+        Ok(request.send().await?)
+    }
+
+    /// Builds the `Authorization` header for a request with an empty query string. `headers`
+    /// must be sorted, lowercase and include `x-amz-content-sha256`. Public so it can be checked
+    /// against AWS's published examples.
+    pub fn authorization(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        amz_date: &str,
+    ) -> String {
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers = headers
+            .iter()
+            .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+            .collect::<String>();
+        let payload_hash = headers
+            .iter()
+            .find(|(name, _)| *name == "x-amz-content-sha256")
+            .map(|(_, value)| *value)
+            .unwrap_or_default();
+        let canonical_request =
+            format!("{method}\n{path}\n\n{canonical_headers}\n{signed_headers}\n{payload_hash}");
+
+        let date = &amz_date[..8];
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request))
+        );
+
+        let mut key = hmac_sha256(
+            format!("AWS4{}", self.secret_access_key).as_bytes(),
+            date.as_bytes(),
+        );
+        for part in [self.region.as_str(), "s3", "aws4_request"] {
+            key = hmac_sha256(&key, part.as_bytes());
+        }
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            self.access_key_id
+        )
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+async fn unexpected(response: reqwest::Response) -> StorageError {
+    let status = response.status();
+    StorageError::Status(status, response.text().await.unwrap_or_default())
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, key: &str, content_type: &str, data: Vec<u8>) -> Result<(), StorageError> {
+        let response = self
+            .send(Method::PUT, key, Some(content_type), data)
+            .await?;
+        if !response.status().is_success() {
+            return Err(unexpected(response).await);
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        let response = self.send(Method::GET, key, None, Vec::new()).await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(response.bytes().await?.to_vec())),
+            _ => Err(unexpected(response).await),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let response = self.send(Method::DELETE, key, None, Vec::new()).await?;
+        if !(response.status().is_success() || response.status() == StatusCode::NOT_FOUND) {
+            return Err(unexpected(response).await);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use axum::{extract::State, http::StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::{ensure_owner_or_manager, Auth},
+    error::AppError,
+    extract::{Json, Path},
+    jwt::Claims,
+    models::{Team, TeamMember, TeamRole, User},
+    posts::find_post,
+    utils::now,
+    AppState,
+};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamResponse {
+    #[serde(flatten)]
+    team: Team,
+    members: Vec<TeamMember>,
+}
+
+impl TeamResponse {
+    async fn load(state: &AppState, team: Team) -> Result<TeamResponse, AppError> {
+        let members = Team::members(&state.pool, team.team_id).await?;
+        Ok(TeamResponse { team, members })
+    }
+
+    fn leader(&self) -> Option<&TeamMember> {
+        self.members
+            .iter()
+            .find(|member| member.role == TeamRole::Leader)
+    }
+}
+
+async fn find_team(state: &AppState, team_id: i32) -> Result<TeamResponse, AppError> {
+    match Team::find_by_id(&state.pool, team_id).await? {
+        Some(team) => TeamResponse::load(state, team).await,
+        None => Err(AppError::not_found("Team not found")),
+    }
+}
+
+/// Checks that the caller leads `team` (or is a manager) and returns the leader's id.
+async fn ensure_leader(
+    state: &AppState,
+    auth: &Claims,
+    team: &TeamResponse,
+) -> Result<i32, AppError> {
+    let Some(leader) = team.leader() else {
+        return Err(AppError::Internal(format!(
+            "team {} has no leader",
+            team.team.team_id
+        )));
+    };
+    ensure_owner_or_manager(state, auth, leader.user_id).await?;
+    Ok(leader.user_id)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTeamBody {
+    /// Defaults to the post's title.
+    name: Option<String>,
+}
+
+pub async fn create_team(
+    State(state): State<AppState>,
+    Path(post_id): Path<i32>,
+    Auth(auth): Auth,
+    Json(body): Json<CreateTeamBody>,
+) -> Result<(StatusCode, Json<TeamResponse>), AppError> {
+    let post = find_post(&state, post_id).await?;
+    ensure_owner_or_manager(&state, &auth, post.user_id).await?;
+
+    let team = Team {
+        post_id: Some(post.post_id),
+        contest_id: post.contest_id,
+        name: body.name.unwrap_or(post.title),
+        created_at: now(),
+        ..Default::default()
+    };
+    let team_id = Team::insert(&state.pool, &team, post.user_id)
+        .await
+        .map_err(|e| match AppError::from(e) {
+            AppError::Conflict(_, details) => {
+                AppError::Conflict("A team was already formed from this post".into(), details)
+            }
+            e => e,
+        })? as i32;
+
+    let team = TeamResponse::load(&state, Team { team_id, ..team }).await?;
+    Ok((StatusCode::CREATED, Json(team)))
+}
+
+pub async fn get_team(
+    State(state): State<AppState>,
+    Path(team_id): Path<i32>,
+) -> Result<Json<TeamResponse>, AppError> {
+    Ok(Json(find_team(&state, team_id).await?))
+}
+
+pub async fn list_user_teams(
+    State(state): State<AppState>,
+    Path(user_id): Path<i32>,
+) -> Result<Json<Vec<TeamResponse>>, AppError> {
+    if User::find_by_id(&state.pool, user_id).await?.is_none() {
+        return Err(AppError::not_found("User not found"));
+    }
+
+    let mut teams = Vec::new();
+    for team in Team::find_by_user_id(&state.pool, user_id).await? {
+        teams.push(TeamResponse::load(&state, team).await?);
+    }
+    Ok(Json(teams))
+}
+
+pub async fn leave_team(
+    State(state): State<AppState>,
+    Path(team_id): Path<i32>,
+    Auth(auth): Auth,
+) -> Result<StatusCode, AppError> {
+    let team = find_team(&state, team_id).await?;
+    if team
+        .leader()
+        .is_some_and(|leader| leader.user_id == auth.sub)
+    {
+        return Err(AppError::unprocessable(
+            "The leader must transfer leadership or disband the team instead",
+        ));
+    }
+
+    if !Team::remove_member(&state.pool, team_id, auth.sub).await? {
+        return Err(AppError::not_found("You are not a member of this team"));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferLeadershipBody {
+    user_id: i32,
+}
+
+pub async fn transfer_leadership(
+    State(state): State<AppState>,
+    Path(team_id): Path<i32>,
+    Auth(auth): Auth,
+    Json(body): Json<TransferLeadershipBody>,
+) -> Result<Json<TeamResponse>, AppError> {
+    let team = find_team(&state, team_id).await?;
+    let leader_id = ensure_leader(&state, &auth, &team).await?;
+
+    if !Team::transfer_leadership(&state.pool, team_id, leader_id, body.user_id).await? {
+        return Err(AppError::unprocessable(
+            "`userId` is not a member of this team",
+        ));
+    }
+    Ok(Json(TeamResponse::load(&state, team.team).await?))
+}
+
+pub async fn disband_team(
+    State(state): State<AppState>,
+    Path(team_id): Path<i32>,
+    Auth(auth): Auth,
+) -> Result<StatusCode, AppError> {
+    let team = find_team(&state, team_id).await?;
+    ensure_leader(&state, &auth, &team).await?;
+
+    Team::delete(&state.pool, team_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,31 +1,316 @@
-use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
-    Json,
+use axum::{extract::State, http::StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    auth::{hash_password, verify_password, Auth, PasswordMatch},
+    avatars::avatar_url,
+    error::AppError,
+    extract::{Json, Path},
+    models::{Session, User},
+    AppState,
 };
 
-use crate::{auth::Auth, models::User, AppState};
+// Longest accepted values in characters, matching the `VARCHAR` sizes of `users`.
+pub const USERNAME_MAX: usize = 10;
+pub const NICKNAME_MAX: usize = 10;
+pub const EMAIL_MAX: usize = 100;
+pub const BIO_MAX: usize = 1000;
+
+/// Collects problems with request fields so they are all reported in one 422 response, as
+/// `details.fields.<name>`.
+#[derive(Default)]
+pub struct Validation(Map<String, Value>);
+
+impl Validation {
+    /// How usernames and nicknames are stored and looked up, so `"bob "` cannot sit next to
+    /// `"bob"` in the unique indexes or fail to log in as him.
+    pub fn normalize_name(value: &str) -> String {
+        value.trim().to_string()
+    }
+
+    /// How emails are stored and looked up.
+    pub fn normalize_email(value: &str) -> String {
+        value.trim().to_lowercase()
+    }
+
+    /// Requires `value` to be non-blank and at most `max` characters long.
+    pub fn text(&mut self, field: &str, value: &str, max: usize) {
+        if value.trim().is_empty() {
+            self.fail(field, "must not be empty".to_string());
+        } else if value.chars().count() > max {
+            self.fail(field, format!("must be at most {max} characters"));
+        }
+    }
+
+    pub fn email(&mut self, field: &str, value: &str) {
+        self.text(field, value, EMAIL_MAX);
+        let valid = value
+            .split_once('@')
+            .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'));
+        if !valid && !self.0.contains_key(field) {
+            self.fail(field, "must be an email address".to_string());
+        }
+    }
+
+    pub fn fail(&mut self, field: &str, message: String) {
+        self.0.insert(field.to_string(), Value::String(message));
+    }
+
+    pub fn finish(self) -> Result<(), AppError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Unprocessable(
+                "Invalid fields".into(),
+                Some(json!({ "fields": self.0 })),
+            ))
+        }
+    }
+}
+
+/// Rewords the 409 from the unique nickname index.
+fn nickname_conflict(e: sqlx::Error) -> AppError {
+    match AppError::from(e) {
+        AppError::Conflict(_, details) => {
+            AppError::Conflict("Nickname is already taken".into(), details)
+        }
+        e => e,
+    }
+}
+
+/// Fails unless `password` is `user`'s current password.
+fn check_current_password(user: &User, password: &str) -> Result<(), AppError> {
+    match verify_password(password, &user.password) {
+        PasswordMatch::Valid | PasswordMatch::Legacy => Ok(()),
+        PasswordMatch::Invalid => Err(AppError::forbidden("Current password is incorrect")),
+    }
+}
+
+async fn find_me(state: &AppState, user_id: i32) -> Result<User, AppError> {
+    User::find_by_id(&state.pool, user_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("User not found"))
+}
+
+/// Profile fields that anyone may see.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicUser {
+    id: i32,
+    username: String,
+    nickname: String,
+    bio: Option<String>,
+    field: i32,
+    avatar_url: Option<String>,
+}
+
+impl From<User> for PublicUser {
+    fn from(user: User) -> PublicUser {
+        PublicUser {
+            avatar_url: user.avatar_hash.map(|hash| avatar_url(user.id, &hash)),
+            id: user.id,
+            username: user.username,
+            nickname: user.nickname,
+            bio: user.bio,
+            field: user.field,
+        }
+    }
+}
+
+/// Everything a user may see about their own account.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivateUser {
+    id: i32,
+    username: String,
+    nickname: String,
+    email: String,
+    bio: Option<String>,
+    field: i32,
+    is_manager: bool,
+    avatar_url: Option<String>,
+}
+
+impl From<User> for PrivateUser {
+    fn from(user: User) -> PrivateUser {
+        PrivateUser {
+            avatar_url: user.avatar_hash.map(|hash| avatar_url(user.id, &hash)),
+            id: user.id,
+            username: user.username,
+            nickname: user.nickname,
+            email: user.email,
+            bio: user.bio,
+            field: user.field,
+            is_manager: user.is_manager,
+        }
+    }
+}
+
+pub async fn me(
+    State(state): State<AppState>,
+    Auth(claims): Auth,
+) -> Result<Json<PrivateUser>, AppError> {
+    match User::find_by_id(&state.pool, claims.sub).await? {
+        Some(user) => Ok(Json(user.into())),
+        None => Err(AppError::not_found("User not found")),
+    }
+}
 
-pub async fn me(State(state): State<AppState>, Auth(claims): Auth) -> impl IntoResponse {
-    if let Some(user) = User::find_by_id(&state.pool, claims.sub).await {
-        (StatusCode::OK, Json(user)).into_response()
-    } else {
-        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateMeBody {
+    nickname: Option<String>,
+    /// An empty string clears the bio.
+    bio: Option<String>,
+    field: Option<i32>,
+}
+
+pub async fn update_me(
+    State(state): State<AppState>,
+    Auth(claims): Auth,
+    Json(body): Json<UpdateMeBody>,
+) -> Result<Json<PrivateUser>, AppError> {
+    let mut user = find_me(&state, claims.sub).await?;
+
+    let mut validation = Validation::default();
+    if let Some(nickname) = body.nickname {
+        let nickname = Validation::normalize_name(&nickname);
+        validation.text("nickname", &nickname, NICKNAME_MAX);
+        user.nickname = nickname;
+    }
+    if let Some(bio) = body.bio {
+        if bio.chars().count() > BIO_MAX {
+            validation.fail("bio", format!("must be at most {BIO_MAX} characters"));
+        }
+        user.bio = Some(bio).filter(|bio| !bio.is_empty());
     }
+    if let Some(field) = body.field {
+        user.field = field;
+    }
+    validation.finish()?;
+
+    User::update_profile(&state.pool, &user)
+        .await
+        .map_err(nickname_conflict)?;
+    Ok(Json(user.into()))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePasswordBody {
+    current_password: String,
+    new_password: String,
 }
 
-pub async fn list_users(State(state): State<AppState>) -> impl IntoResponse {
-    Json(User::find_all(&state.pool).await)
+/// Changes the password and signs out every other session.
+pub async fn change_password(
+    State(state): State<AppState>,
+    Auth(claims): Auth,
+    Json(body): Json<ChangePasswordBody>,
+) -> Result<StatusCode, AppError> {
+    let user = find_me(&state, claims.sub).await?;
+    check_current_password(&user, &body.current_password)?;
+    if body.new_password.is_empty() {
+        let mut validation = Validation::default();
+        validation.fail("newPassword", "must not be empty".to_string());
+        validation.finish()?;
+    }
+
+    let password = hash_password(&body.new_password)?;
+    User::update_password(&state.pool, user.id, &password).await?;
+    Session::revoke_others(&state.pool, user.id, claims.sid).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEmailBody {
+    current_password: String,
+    email: String,
+}
+
+pub async fn change_email(
+    State(state): State<AppState>,
+    Auth(claims): Auth,
+    Json(body): Json<ChangeEmailBody>,
+) -> Result<Json<PrivateUser>, AppError> {
+    let mut user = find_me(&state, claims.sub).await?;
+    check_current_password(&user, &body.current_password)?;
+    let email = Validation::normalize_email(&body.email);
+    let mut validation = Validation::default();
+    validation.email("email", &email);
+    validation.finish()?;
+
+    User::update_email(&state.pool, user.id, &email)
+        .await
+        .map_err(|e| match AppError::from(e) {
+            AppError::Conflict(_, details) => {
+                AppError::Conflict("Email is already in use".into(), details)
+            }
+            e => e,
+        })?;
+    user.email = email;
+    Ok(Json(user.into()))
+}
+
+pub async fn list_users(State(state): State<AppState>) -> Result<Json<Vec<PublicUser>>, AppError> {
+    let users = User::find_all(&state.pool).await?;
+    Ok(Json(users.into_iter().map(PublicUser::from).collect()))
 }
 
 pub async fn get_user(
     State(state): State<AppState>,
     Path(user_id): Path<i32>,
-) -> impl IntoResponse {
-    if let Some(user) = User::find_by_id(&state.pool, user_id).await {
-        (StatusCode::OK, Json(user)).into_response()
-    } else {
-        StatusCode::NOT_FOUND.into_response()
+) -> Result<Json<PublicUser>, AppError> {
+    match User::find_by_id(&state.pool, user_id).await? {
+        Some(user) => Ok(Json(user.into())),
+        None => Err(AppError::not_found("User not found")),
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResponse {
+    id: i32,
+    user_agent: Option<String>,
+    ip: Option<String>,
+    created_at: i64,
+    last_seen_at: i64,
+    /// Whether this is the session the request was made with.
+    current: bool,
+}
+
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    Auth(claims): Auth,
+) -> Result<Json<Vec<SessionResponse>>, AppError> {
+    let sessions = Session::find_active_by_user_id(&state.pool, claims.sub).await?;
+    Ok(Json(
+        sessions
+            .into_iter()
+            .map(|session| SessionResponse {
+                id: session.id,
+                user_agent: session.user_agent,
+                ip: session.ip,
+                created_at: session.created_at,
+                last_seen_at: session.last_seen_at,
+                current: session.id == claims.sid,
+            })
+            .collect(),
+    ))
+}
+
+pub async fn delete_session(
+    State(state): State<AppState>,
+    Auth(claims): Auth,
+    Path(session_id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    match Session::find_by_id(&state.pool, session_id).await? {
+        Some(session) if session.user_id == claims.sub && !session.revoked => {
+            Session::revoke(&state.pool, session.id).await?;
+            Ok(StatusCode::NO_CONTENT)
+        }
+        _ => Err(AppError::not_found("Session not found")),
     }
 }
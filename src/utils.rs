@@ -1,8 +1,22 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
 pub fn now() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs() as i64
 }
+
+/// Returns 32 random bytes, hex encoded.
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+pub fn sha256_hex(value: impl AsRef<[u8]>) -> String {
+    hex::encode(Sha256::digest(value))
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+use common::TestApp;
+
+#[tokio::test]
+async fn accepting_fills_and_closes_the_post() {
+    let app = TestApp::new().await;
+    let (_, author) = app.user("alice").await;
+    let (_, bob) = app.user("bob").await;
+    let (_, carol) = app.user("carol").await;
+    let (_, dave) = app.user("dave").await;
+    let post_id = app.post(&author, "team").await;
+    app.request(
+        Method::PATCH,
+        &format!("/posts/{post_id}"),
+        Some(&author),
+        Some(json!({ "max": 2 })),
+    )
+    .await;
+
+    let (status, _) = app.apply(&author, post_id).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, bob_app) = app.apply(&bob, post_id).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = app.apply(&bob, post_id).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (_, carol_app) = app.apply(&carol, post_id).await;
+
+    // Only the author sees the applications.
+    let uri = format!("/posts/{post_id}/applications");
+    let (status, _) = app.request(Method::GET, &uri, Some(&bob), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(
+        app.decide(&bob, post_id, &bob_app, "accept").await,
+        StatusCode::FORBIDDEN
+    );
+
+    assert_eq!(
+        app.decide(&author, post_id, &bob_app, "accept").await,
+        StatusCode::OK
+    );
+    let (_, post) = app
+        .request(Method::GET, &format!("/posts/{post_id}"), None, None)
+        .await;
+    assert_eq!(post["ppl"], 2);
+
+    let (_, applications) = app.request(Method::GET, &uri, Some(&author), None).await;
+    assert_eq!(applications[0]["status"], "accepted");
+    assert_eq!(applications[0]["nickname"], "bob");
+    assert_eq!(applications[1]["status"], "rejected");
+
+    assert_eq!(
+        app.decide(&author, post_id, &carol_app, "accept").await,
+        StatusCode::CONFLICT
+    );
+    let (status, _) = app.apply(&dave, post_id).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn withdraw_and_reject() {
+    let app = TestApp::new().await;
+    let (_, author) = app.user("alice").await;
+    let (_, bob) = app.user("bob").await;
+    let post_id = app.post(&author, "team").await;
+
+    let (_, first) = app.apply(&bob, post_id).await;
+    let application_id = first["applicationId"].as_i64().unwrap();
+    let uri = format!("/posts/{post_id}/applications/{application_id}");
+    let (status, _) = app.request(Method::DELETE, &uri, Some(&author), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.request(Method::DELETE, &uri, Some(&bob), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = app.request(Method::DELETE, &uri, Some(&bob), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(
+        app.decide(&author, post_id, &first, "accept").await,
+        StatusCode::CONFLICT
+    );
+
+    // Withdrawing frees the user to apply again.
+    let (status, second) = app.apply(&bob, post_id).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(
+        app.decide(&author, post_id, &second, "reject").await,
+        StatusCode::OK
+    );
+    let (_, post) = app
+        .request(Method::GET, &format!("/posts/{post_id}"), None, None)
+        .await;
+    assert_eq!(post["ppl"], 1);
+
+    let (status, _) = app
+        .request(
+            Method::DELETE,
+            &format!("/posts/{post_id}"),
+            Some(&author),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
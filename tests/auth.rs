@@ -0,0 +1,95 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+use common::TestApp;
+
+#[tokio::test]
+async fn refresh_rotates_and_detects_reuse() {
+    let app = TestApp::new().await;
+    let body = app.signup("alice").await;
+    let first = body["refreshToken"].as_str().unwrap();
+
+    let (status, body) = app
+        .request(
+            Method::POST,
+            "/token/refresh",
+            None,
+            Some(json!({ "refreshToken": first })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let second = body["refreshToken"].as_str().unwrap().to_string();
+    assert_ne!(first, second);
+
+    // Replaying the rotated token revokes the session, so the fresh token stops working too.
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/token/refresh",
+            None,
+            Some(json!({ "refreshToken": first })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/token/refresh",
+            None,
+            Some(json!({ "refreshToken": second })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn revoked_session_rejects_access_token() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+
+    let (status, sessions) = app
+        .request(Method::GET, "/users/@me/sessions", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(sessions[0]["current"], true);
+    let session_id = sessions[0]["id"].as_i64().unwrap();
+
+    let (status, _) = app
+        .request(
+            Method::DELETE,
+            &format!("/users/@me/sessions/{session_id}"),
+            Some(&token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) = app
+        .request(Method::GET, "/users/@me", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn bulk_delete_requires_manager() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+    let (_, manager) = app.manager("boss").await;
+
+    let (status, _) = app.request(Method::DELETE, "/posts", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, body) = app
+        .request(Method::DELETE, "/posts", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "forbidden");
+
+    let (status, _) = app
+        .request(Method::DELETE, "/posts", Some(&manager), None)
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
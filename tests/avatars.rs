@@ -0,0 +1,138 @@
+mod common;
+
+use axum::{
+    body::to_bytes,
+    http::{header, Method, StatusCode},
+};
+use sagongsa_server::migrate;
+use serde_json::Value;
+
+use common::{memory_pool, png, TestApp};
+
+async fn upload(app: &TestApp, token: &str, file: &[u8]) -> (StatusCode, Value) {
+    app.upload(
+        Method::PUT,
+        "/users/@me/avatar",
+        token,
+        "avatar",
+        "image/png",
+        file,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn avatars_are_resized_and_revalidated() {
+    let app = TestApp::new().await;
+    let (id, token) = app.user("alice").await;
+    let uri = format!("/users/{id}/avatar");
+
+    let response = app.fetch(&uri, None).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let (status, me) = upload(&app, &token, &png(300, 200)).await;
+    assert_eq!(status, StatusCode::OK);
+    let avatar_url = me["avatarUrl"].as_str().unwrap();
+    assert!(avatar_url.starts_with(&format!("{uri}?v=")));
+    let (_, user) = app
+        .request(Method::GET, &format!("/users/{id}"), None, None)
+        .await;
+    assert_eq!(user["avatarUrl"], avatar_url);
+
+    for size in [256, 64] {
+        let response = app.fetch(&format!("{uri}?size={size}"), None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/jpeg");
+        let etag = response.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let image = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (size, size));
+
+        let response = app.fetch(&format!("{uri}?size={size}"), Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    }
+    let response = app.fetch(&format!("{uri}?size=100"), None).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // A new upload changes the ETag, so old copies are fetched again.
+    let etag = app.fetch(&uri, None).await.headers()[header::ETAG].clone();
+    let (status, me) = upload(&app, &token, &png(64, 64)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(me["avatarUrl"], avatar_url);
+    let response = app.fetch(&uri, etag.to_str().ok()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let (status, _) = app
+        .request(Method::DELETE, "/users/@me/avatar", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(app.fetch(&uri, None).await.status(), StatusCode::NOT_FOUND);
+    let (_, me) = app
+        .request(Method::GET, "/users/@me", Some(&token), None)
+        .await;
+    assert!(me["avatarUrl"].is_null());
+}
+
+#[tokio::test]
+async fn avatar_uploads_reject_bad_files() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+
+    let (status, _) = upload(&app, &token, b"GIF89a but not really an image").await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = upload(&app, &token, b"plain text").await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, body) = upload(&app, &token, &vec![0; 3 * 1024 * 1024]).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(body["code"], "payload_too_large");
+}
+
+#[tokio::test]
+async fn avatars_stored_in_the_database_are_moved_into_storage() {
+    // A database from before migration 0012, when avatars were BLOBs.
+    let pool = memory_pool().await;
+    migrate::up(&pool, Some(11)).await.unwrap();
+    let hash = "ab".repeat(32);
+    sqlx::query(
+        "INSERT INTO users (id, username, password, nickname, email, is_manager, is_withdrawn, avatar_hash) \
+         VALUES (1, 'alice', 'alice-password', 'alice', 'alice@example.com', FALSE, FALSE, ?)",
+    )
+    .bind(&hash)
+    .execute(&pool)
+    .await
+    .unwrap();
+    for size in [256, 64] {
+        sqlx::query(
+            "INSERT INTO avatars (user_id, size, content_type, data) VALUES (1, ?, 'image/png', ?)",
+        )
+        .bind(size)
+        .bind(png(size, size))
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let app = TestApp::with_pool(pool).await;
+    assert_eq!(app.state.import_legacy_avatars().await.unwrap(), 1);
+    assert_eq!(app.state.import_legacy_avatars().await.unwrap(), 0);
+    assert_eq!(app.state.clean_up_files().await.unwrap(), 0);
+
+    let (_, user) = app.request(Method::GET, "/users/1", None, None).await;
+    assert_eq!(
+        user["avatarUrl"],
+        format!("/users/1/avatar?v={}", &hash[..16])
+    );
+    let response = app.fetch("/users/1/avatar?size=64", None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+    assert_eq!(
+        response.headers()[header::ETAG],
+        format!("\"{hash}-64\"").as_str()
+    );
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&bytes[..], png(64, 64));
+}
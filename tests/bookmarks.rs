@@ -0,0 +1,80 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+
+use common::TestApp;
+
+#[tokio::test]
+async fn bookmarks_are_listed_and_flagged() {
+    let app = TestApp::new().await;
+    let (_, manager) = app.manager("boss").await;
+    let (_, alice) = app.user("alice").await;
+    let (_, bob) = app.user("bob").await;
+    let contest_id = app.contest(&manager, "contest").await;
+    let first = app.post(&bob, "first").await;
+    let second = app.post(&bob, "second").await;
+
+    for uri in [
+        format!("/contests/{contest_id}/bookmark"),
+        format!("/posts/{first}/bookmark"),
+        format!("/posts/{second}/bookmark"),
+        format!("/posts/{second}/bookmark"),
+    ] {
+        let (status, _) = app.request(Method::PUT, &uri, Some(&alice), None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+    let (status, _) = app
+        .request(Method::PUT, "/posts/999/bookmark", Some(&alice), None)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let orphans: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM bookmarks WHERE item_id = 999")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(orphans, 0);
+
+    let (status, saved) = app
+        .request(Method::GET, "/users/@me/bookmarks", Some(&alice), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(saved["contests"][0]["contestId"], contest_id);
+    assert_eq!(saved["contests"][0]["bookmarkedByMe"], true);
+    let posts = saved["posts"].as_array().unwrap();
+    assert_eq!(posts.len(), 2);
+    assert!(posts.iter().all(|post| post["likedByMe"] == false));
+
+    let (_, contest) = app
+        .request(
+            Method::GET,
+            &format!("/contests/{contest_id}"),
+            Some(&alice),
+            None,
+        )
+        .await;
+    assert_eq!(contest["bookmarkedByMe"], true);
+    let (_, page) = app.request(Method::GET, "/posts", Some(&bob), None).await;
+    assert_eq!(page["items"][0]["bookmarkedByMe"], false);
+    let (_, page) = app.request(Method::GET, "/posts", None, None).await;
+    assert!(page["items"][0].get("bookmarkedByMe").is_none());
+
+    let (status, _) = app
+        .request(
+            Method::DELETE,
+            &format!("/posts/{first}/bookmark"),
+            Some(&alice),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    app.request(
+        Method::DELETE,
+        &format!("/posts/{second}"),
+        Some(&bob),
+        None,
+    )
+    .await;
+    let (_, saved) = app
+        .request(Method::GET, "/users/@me/bookmarks", Some(&alice), None)
+        .await;
+    assert!(saved["posts"].as_array().unwrap().is_empty());
+}
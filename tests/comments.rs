@@ -0,0 +1,251 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+use common::TestApp;
+
+#[tokio::test]
+async fn editing_sets_edited_at() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+    let (_, other) = app.user("bob").await;
+    let post_id = app.post(&token, "post").await;
+    let comment_id = app.comment(&token, post_id, None).await;
+    let uri = format!("/posts/{post_id}/comments/{comment_id}");
+
+    let (status, _) = app
+        .request(
+            Method::PATCH,
+            &uri,
+            Some(&other),
+            Some(json!({ "content": "hijacked" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, comment) = app
+        .request(
+            Method::PATCH,
+            &uri,
+            Some(&token),
+            Some(json!({ "content": "edited" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(comment["content"], "edited");
+    assert!(comment["editedAt"].is_i64());
+}
+
+#[tokio::test]
+async fn deleting_comment_with_replies_leaves_tombstone() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+    let post_id = app.post(&token, "post").await;
+    let parent = app.comment(&token, post_id, None).await;
+    let reply = app.comment(&token, post_id, Some(parent)).await;
+
+    let (status, _) = app
+        .request(
+            Method::DELETE,
+            &format!("/posts/{post_id}/comments/{parent}"),
+            Some(&token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, comments) = app
+        .request(
+            Method::GET,
+            &format!("/posts/{post_id}/comments"),
+            None,
+            None,
+        )
+        .await;
+    let comments = comments.as_array().unwrap();
+    assert_eq!(comments.len(), 2);
+    let tombstone = comments.iter().find(|c| c["commentId"] == parent).unwrap();
+    assert_eq!(tombstone["isDeleted"], true);
+    assert_eq!(tombstone["content"], "");
+    assert!(tombstone["userId"].is_null());
+    assert!(tombstone["nickname"].is_null());
+    let reply_row = comments.iter().find(|c| c["commentId"] == reply).unwrap();
+    assert_eq!(reply_row["nickname"], "alice");
+
+    // Removing the last reply also clears the tombstone it was holding up.
+    let (status, _) = app
+        .request(
+            Method::DELETE,
+            &format!("/posts/{post_id}/comments/{reply}"),
+            Some(&token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, comments) = app
+        .request(
+            Method::GET,
+            &format!("/posts/{post_id}/comments"),
+            None,
+            None,
+        )
+        .await;
+    assert!(comments.as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn comment_must_belong_to_post_in_path() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+    let post_id = app.post(&token, "post").await;
+    let other_post = app.post(&token, "other").await;
+    let comment_id = app.comment(&token, post_id, None).await;
+
+    let (status, _) = app
+        .request(
+            Method::DELETE,
+            &format!("/posts/{other_post}/comments/{comment_id}"),
+            Some(&token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn tree_format_nests_replies_up_to_max_depth() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+    let post_id = app.post(&token, "post").await;
+    let root = app.comment(&token, post_id, None).await;
+    let reply = app.comment(&token, post_id, Some(root)).await;
+    let nested = app.comment(&token, post_id, Some(reply)).await;
+    let other_root = app.comment(&token, post_id, None).await;
+
+    let (status, tree) = app
+        .request(
+            Method::GET,
+            &format!("/posts/{post_id}/comments?format=tree"),
+            None,
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tree[0]["commentId"], other_root);
+    assert_eq!(tree[1]["commentId"], root);
+    assert_eq!(tree[1]["replyCount"], 2);
+    assert_eq!(tree[1]["replies"][0]["commentId"], reply);
+    assert_eq!(tree[1]["replies"][0]["replies"][0]["commentId"], nested);
+
+    let (_, tree) = app
+        .request(
+            Method::GET,
+            &format!("/posts/{post_id}/comments?format=tree&maxDepth=1"),
+            None,
+            None,
+        )
+        .await;
+    let replies = tree[1]["replies"].as_array().unwrap();
+    assert_eq!(replies.len(), 2);
+    assert_eq!(replies[1]["commentId"], nested);
+    assert!(replies[0]["replies"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn tree_format_handles_very_deep_threads() {
+    let app = TestApp::new().await;
+    let (user_id, token) = app.user("alice").await;
+    let post_id = app.post(&token, "post").await;
+    // A chain of replies far deeper than any stack would allow recursing over.
+    sqlx::query(
+        r#"
+        WITH RECURSIVE chain(id) AS (SELECT 1 UNION ALL SELECT id + 1 FROM chain WHERE id < 20000)
+        INSERT INTO comments (comment_id, post_id, user_id, content, created_at, parent)
+        SELECT id, ?, ?, 'reply', 0, NULLIF(id - 1, 0) FROM chain
+        "#,
+    )
+    .bind(post_id)
+    .bind(user_id)
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    let (status, tree) = app
+        .request(
+            Method::GET,
+            &format!("/posts/{post_id}/comments?format=tree&maxDepth=1000000"),
+            None,
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tree[0]["replyCount"], 19999);
+    // `maxDepth` is capped, and everything below the last level is flattened onto it.
+    let mut node = &tree[0];
+    for _ in 1..20 {
+        node = &node["replies"][0];
+    }
+    assert_eq!(node["replyCount"], 19980);
+    assert_eq!(node["replies"].as_array().unwrap().len(), 19980);
+}
+
+#[tokio::test]
+async fn tree_format_shows_comments_on_parent_cycles() {
+    let app = TestApp::new().await;
+    let (user_id, token) = app.user("alice").await;
+    let post_id = app.post(&token, "post").await;
+    // Old rows: 1 is its own parent, 2 and 3 are each other's, and 4 replies to 3.
+    for (comment_id, parent) in [(1, 1), (2, 3), (3, 2), (4, 3)] {
+        sqlx::query(
+            "INSERT INTO comments (comment_id, post_id, user_id, content, created_at, parent) \
+             VALUES (?, ?, ?, 'old', 0, ?)",
+        )
+        .bind(comment_id)
+        .bind(post_id)
+        .bind(user_id)
+        .bind(parent)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    }
+
+    let (status, tree) = app
+        .request(
+            Method::GET,
+            &format!("/posts/{post_id}/comments?format=tree"),
+            None,
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let roots = tree
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|node| node["commentId"].as_i64().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(roots, [3, 2, 1]);
+    assert_eq!(tree[0]["replyCount"], 1);
+    assert_eq!(tree[0]["replies"][0]["commentId"], 4);
+}
+
+#[tokio::test]
+async fn parent_must_be_on_same_post() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+    let post_id = app.post(&token, "post").await;
+    let other_post = app.post(&token, "other").await;
+    let foreign = app.comment(&token, other_post, None).await;
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            &format!("/posts/{post_id}/comments"),
+            Some(&token),
+            Some(json!({ "content": "reply", "parent": foreign })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
@@ -0,0 +1,374 @@
+#![allow(dead_code)]
+
+use std::{
+    env, fs,
+    io::Cursor,
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Method, Request, StatusCode},
+    response::Response,
+    Router,
+};
+use image::{ImageFormat, Rgb, RgbImage};
+use jsonwebtoken::Algorithm;
+use sagongsa_server::{
+    config::{Config, StorageConfig},
+    jwt::{JwtConfig, JwtKeyConfig},
+    migrate, router, AppState,
+};
+use serde_json::{json, Value};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    SqlitePool,
+};
+use tower::ServiceExt;
+
+/// Encodes a `width` by `height` opaque gradient as PNG.
+pub fn png(width: u32, height: u32) -> Vec<u8> {
+    let image = RgbImage::from_fn(width, height, |x, y| Rgb([x as u8, y as u8, 128]));
+    let mut bytes = Cursor::new(Vec::new());
+    image.write_to(&mut bytes, ImageFormat::Png).unwrap();
+    bytes.into_inner()
+}
+
+/// An empty in-memory database.
+pub async fn memory_pool() -> SqlitePool {
+    // Every connection to `sqlite::memory:` opens its own database, so keep exactly one alive.
+    SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap()
+}
+
+/// An empty database file in the temp directory, opened the way `main` opens one so several
+/// connections can contend for it. Delete the returned path when done.
+pub async fn file_pool() -> (SqlitePool, PathBuf) {
+    static FILES: AtomicUsize = AtomicUsize::new(0);
+    let path = env::temp_dir().join(format!(
+        "sagongsa-test-{}-{}.db",
+        process::id(),
+        FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let pool = SqlitePoolOptions::new()
+        .max_connections(8)
+        .connect_with(
+            SqliteConnectOptions::new()
+                .filename(&path)
+                .create_if_missing(true),
+        )
+        .await
+        .unwrap();
+    (pool, path)
+}
+
+pub struct TestApp {
+    pub router: Router,
+    pub pool: SqlitePool,
+    pub state: AppState,
+    /// Where the local storage backend keeps uploads. Removed on drop.
+    pub upload_dir: PathBuf,
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.upload_dir);
+    }
+}
+
+impl TestApp {
+    /// Builds the real router on top of a fresh in-memory database.
+    pub async fn new() -> TestApp {
+        TestApp::with_pool(memory_pool().await).await
+    }
+
+    /// Migrates `pool` and builds the real router on top of it.
+    pub async fn with_pool(pool: SqlitePool) -> TestApp {
+        migrate::up(&pool, None).await.unwrap();
+
+        static APPS: AtomicUsize = AtomicUsize::new(0);
+        let upload_dir = env::temp_dir().join(format!(
+            "sagongsa-test-{}-{}",
+            process::id(),
+            APPS.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let config = Config {
+            jwt: JwtConfig {
+                issuer: "test".into(),
+                audience: "test".into(),
+                active_kid: "test".into(),
+                keys: vec![JwtKeyConfig {
+                    kid: "test".into(),
+                    algorithm: Algorithm::HS256,
+                    secret: Some("test-secret".into()),
+                    private_key: None,
+                    public_key: None,
+                }],
+                ..Default::default()
+            },
+            storage: StorageConfig {
+                path: upload_dir.clone(),
+                // Lets tests clean up orphans without waiting.
+                orphan_grace: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let state = AppState::new(pool.clone(), config).unwrap();
+        TestApp {
+            router: router(state.clone()),
+            pool,
+            state,
+            upload_dir,
+        }
+    }
+
+    pub async fn request(
+        &self,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (status, body)
+    }
+
+    /// Sends a multipart body with a part per `(name, content type, data)`.
+    pub async fn multipart(
+        &self,
+        method: Method,
+        uri: &str,
+        token: &str,
+        parts: &[(&str, &str, &[u8])],
+    ) -> (StatusCode, Value) {
+        const BOUNDARY: &str = "test-boundary";
+        let mut body = Vec::new();
+        for (name, content_type, data) in parts {
+            body.extend_from_slice(
+                format!(
+                    "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{name}\"; filename=\"upload\"\r\n\
+                     Content-Type: {content_type}\r\n\r\n"
+                )
+                .as_bytes(),
+            );
+            body.extend_from_slice(data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{BOUNDARY}--\r\n").as_bytes());
+
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={BOUNDARY}"),
+            )
+            .body(Body::from(body))
+            .unwrap();
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    /// Sends `file` as the only part, named `field`, of a multipart body.
+    pub async fn upload(
+        &self,
+        method: Method,
+        uri: &str,
+        token: &str,
+        field: &str,
+        content_type: &str,
+        file: &[u8],
+    ) -> (StatusCode, Value) {
+        self.multipart(method, uri, token, &[(field, content_type, file)])
+            .await
+    }
+
+    /// GETs `uri` without decoding the response, for files.
+    pub async fn fetch(&self, uri: &str, if_none_match: Option<&str>) -> Response {
+        let mut request = Request::builder().uri(uri);
+        if let Some(etag) = if_none_match {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        self.router
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    /// Signs up `username` with a derived email and password, returning the response body.
+    pub async fn signup(&self, username: &str) -> Value {
+        let (status, body) = self
+            .request(
+                Method::POST,
+                "/signup",
+                None,
+                Some(json!({
+                    "username": username,
+                    "password": format!("{username}-password"),
+                    "nickname": username,
+                    "email": format!("{username}@example.com"),
+                })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+        body
+    }
+
+    /// Signs up `username` and returns its user id and access token.
+    pub async fn user(&self, username: &str) -> (i64, String) {
+        let body = self.signup(username).await;
+        (
+            body["user"]["id"].as_i64().unwrap(),
+            body["token"].as_str().unwrap().to_string(),
+        )
+    }
+
+    /// Logs in as `username` created by [`TestApp::signup`], starting a new session, and
+    /// returns its access token.
+    pub async fn login(&self, username: &str) -> String {
+        let (status, body) = self
+            .request(
+                Method::POST,
+                "/login",
+                None,
+                Some(json!({
+                    "username": username,
+                    "password": format!("{username}-password"),
+                })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        body["token"].as_str().unwrap().to_string()
+    }
+
+    /// Signs up `username` and grants it the manager role.
+    pub async fn manager(&self, username: &str) -> (i64, String) {
+        let (id, token) = self.user(username).await;
+        sqlx::query("UPDATE users SET is_manager = TRUE WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .unwrap();
+        (id, token)
+    }
+
+    /// Creates a recruitment post as the owner of `token` and returns its id.
+    pub async fn post(&self, token: &str, title: &str) -> i64 {
+        let (status, body) = self
+            .request(
+                Method::POST,
+                "/posts",
+                Some(token),
+                Some(json!({
+                    "title": title,
+                    "content": "content",
+                    "max": 4,
+                    "ppl": 1,
+                    "desiredField": 1,
+                    "endedAt": 4_000_000_000_i64,
+                })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+        body["postId"].as_i64().unwrap()
+    }
+
+    /// Comments on `post_id` as the owner of `token` and returns the comment id.
+    pub async fn comment(&self, token: &str, post_id: i64, parent: Option<i64>) -> i64 {
+        let (status, body) = self
+            .request(
+                Method::POST,
+                &format!("/posts/{post_id}/comments"),
+                Some(token),
+                Some(json!({ "content": "comment", "parent": parent })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+        body["commentId"].as_i64().unwrap()
+    }
+
+    /// Creates a contest as the manager owning `token` and returns its id.
+    pub async fn contest(&self, token: &str, title: &str) -> i64 {
+        let (status, body) = self
+            .request(
+                Method::POST,
+                "/contests",
+                Some(token),
+                Some(json!({
+                    "title": title,
+                    "field": 1,
+                    "startedAt": 1_000,
+                    "endedAt": 2_000,
+                    "prize": "prize",
+                    "link": "https://example.com",
+                    "img": null,
+                    "ratio": "1:1",
+                })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+        body["contestId"].as_i64().unwrap()
+    }
+
+    /// Applies to `post_id` as the owner of `token`.
+    pub async fn apply(&self, token: &str, post_id: i64) -> (StatusCode, Value) {
+        self.request(
+            Method::POST,
+            &format!("/posts/{post_id}/applications"),
+            Some(token),
+            Some(json!({ "message": "let me in" })),
+        )
+        .await
+    }
+
+    /// Runs `action` (`accept` or `reject`) on the application created by [`TestApp::apply`].
+    pub async fn decide(
+        &self,
+        token: &str,
+        post_id: i64,
+        application: &Value,
+        action: &str,
+    ) -> StatusCode {
+        let application_id = application["applicationId"].as_i64().unwrap();
+        self.request(
+            Method::POST,
+            &format!("/posts/{post_id}/applications/{application_id}/{action}"),
+            Some(token),
+            None,
+        )
+        .await
+        .0
+    }
+}
@@ -0,0 +1,114 @@
+use sagongsa_server::config::{Config, ConfigError};
+
+fn no_env(_: &str) -> Option<String> {
+    None
+}
+
+#[test]
+fn example_config_is_valid() {
+    let text = include_str!("../config.example.toml");
+    let config = Config::parse("config.example.toml", text, no_env).unwrap();
+    assert_eq!(config.listen_addr.port(), 4000);
+    assert_eq!(config.jwt.active_kid, "2026-10");
+}
+
+#[test]
+fn env_overrides_file() {
+    let text = r#"
+        pool_size = 2
+
+        [jwt]
+        active_kid = "main"
+    "#;
+    let env = |name: &str| match name {
+        "SAGONGSA_POOL_SIZE" => Some("8".to_string()),
+        "SAGONGSA_CORS_ORIGINS" => Some("https://a.example, https://b.example".to_string()),
+        "SAGONGSA_JWT_SECRET" => Some("secret".to_string()),
+        _ => None,
+    };
+
+    let config = Config::parse("test.toml", text, env).unwrap();
+    assert_eq!(config.pool_size, 8);
+    assert_eq!(
+        config.cors_origins,
+        ["https://a.example", "https://b.example"]
+    );
+    assert_eq!(config.jwt.keys[0].kid, "main");
+}
+
+#[test]
+fn unprefixed_jwt_secret_is_still_accepted() {
+    let text = r#"
+        [jwt]
+        active_kid = "main"
+    "#;
+    let env = |name: &str| (name == "JWT_SECRET").then(|| "old".to_string());
+    let config = Config::parse("test.toml", text, env).unwrap();
+    assert_eq!(config.jwt.keys[0].secret.as_deref(), Some("old"));
+
+    let env = |name: &str| match name {
+        "JWT_SECRET" => Some("old".to_string()),
+        "SAGONGSA_JWT_SECRET" => Some("new".to_string()),
+        _ => None,
+    };
+    let config = Config::parse("test.toml", text, env).unwrap();
+    assert_eq!(config.jwt.keys[0].secret.as_deref(), Some("new"));
+}
+
+#[test]
+fn validation_lists_every_problem() {
+    let text = r#"
+        database_url = "postgres://localhost"
+        pool_size = 0
+        cors_origins = ["localhost:3000"]
+
+        [upload]
+        max_bytes = 1024
+        max_image_bytes = 2048
+    "#;
+
+    let Err(ConfigError::Invalid(problems)) = Config::parse("test.toml", text, no_env) else {
+        panic!("expected validation to fail");
+    };
+    // database_url, pool_size, cors_origins, max_image_bytes and the missing JWT key.
+    assert_eq!(problems.len(), 5, "{problems:?}");
+}
+
+#[test]
+fn malformed_env_value_is_reported() {
+    let env = |name: &str| (name == "SAGONGSA_POOL_SIZE").then(|| "many".to_string());
+    let error = Config::parse("test.toml", "", env).unwrap_err();
+    assert!(matches!(error, ConfigError::Env(..)));
+    assert!(error.to_string().contains("SAGONGSA_POOL_SIZE"));
+}
+
+#[test]
+fn s3_storage_needs_bucket_and_credentials() {
+    let text = r#"
+        [storage]
+        backend = "s3"
+
+        [storage.s3]
+        endpoint = "http://localhost:9000"
+
+        [jwt]
+        active_kid = "main"
+    "#;
+    let env = |name: &str| (name == "SAGONGSA_JWT_SECRET").then(|| "secret".to_string());
+    let Err(ConfigError::Invalid(problems)) = Config::parse("test.toml", text, env) else {
+        panic!("expected validation to fail");
+    };
+    // The bucket and the credentials. The region has a default.
+    assert_eq!(problems.len(), 2, "{problems:?}");
+
+    let env = |name: &str| match name {
+        "SAGONGSA_JWT_SECRET" => Some("secret".to_string()),
+        "SAGONGSA_STORAGE_S3_BUCKET" => Some("uploads".to_string()),
+        "SAGONGSA_STORAGE_S3_ACCESS_KEY_ID" => Some("key".to_string()),
+        "SAGONGSA_STORAGE_S3_SECRET_ACCESS_KEY" => Some("secret".to_string()),
+        _ => None,
+    };
+    let config = Config::parse("test.toml", text, env).unwrap();
+    assert_eq!(config.storage.s3.bucket, "uploads");
+    assert_eq!(config.storage.s3.region, "us-east-1");
+}
@@ -0,0 +1,503 @@
+mod common;
+
+use axum::{
+    body::to_bytes,
+    http::{Method, StatusCode},
+};
+use sagongsa_server::migrate;
+use serde_json::{json, Value};
+
+use common::{memory_pool, png, TestApp};
+
+#[tokio::test]
+async fn creating_contests_requires_manager() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/contests",
+            Some(&token),
+            Some(json!({
+                "title": "contest",
+                "field": 1,
+                "startedAt": 1_000,
+                "endedAt": 2_000,
+                "prize": "prize",
+                "link": "https://example.com",
+                "ratio": "1:1",
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn patch_contest_validates_dates() {
+    let app = TestApp::new().await;
+    let (_, manager) = app.manager("boss").await;
+    let (_, other) = app.user("alice").await;
+    let contest_id = app.contest(&manager, "before").await;
+    let uri = format!("/contests/{contest_id}");
+
+    let (status, contest) = app
+        .request(
+            Method::PATCH,
+            &uri,
+            Some(&manager),
+            Some(json!({ "title": "after", "endedAt": 3_000 })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(contest["title"], "after");
+    assert_eq!(contest["endedAt"], 3_000);
+
+    let (status, _) = app
+        .request(
+            Method::PATCH,
+            &uri,
+            Some(&manager),
+            Some(json!({ "startedAt": 5_000 })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, _) = app
+        .request(
+            Method::PATCH,
+            &uri,
+            Some(&other),
+            Some(json!({ "title": "hijacked" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn create_contest_validates_dates() {
+    let app = TestApp::new().await;
+    let (_, manager) = app.manager("boss").await;
+
+    let (status, body) = app
+        .request(
+            Method::POST,
+            "/contests",
+            Some(&manager),
+            Some(json!({
+                "title": "backwards",
+                "field": 1,
+                "startedAt": 5_000,
+                "endedAt": 2_000,
+                "prize": "prize",
+                "link": "https://example.com",
+                "ratio": "1:1",
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+    let (_, page) = app.request(Method::GET, "/contests", None, None).await;
+    assert_eq!(page["items"], json!([]));
+}
+
+#[tokio::test]
+async fn deleting_contest_detaches_linked_posts() {
+    let app = TestApp::new().await;
+    let (_, manager) = app.manager("boss").await;
+    let (_, token) = app.user("alice").await;
+    let contest_id = app.contest(&manager, "contest").await;
+
+    let (status, body) = app
+        .request(
+            Method::POST,
+            "/posts",
+            Some(&token),
+            Some(json!({
+                "contestId": contest_id,
+                "title": "team",
+                "content": "content",
+                "max": 4,
+                "ppl": 1,
+                "desiredField": 1,
+                "endedAt": 4_000,
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let post_id = body["postId"].as_i64().unwrap();
+
+    let (status, _) = app
+        .request(
+            Method::DELETE,
+            &format!("/contests/{contest_id}"),
+            Some(&manager),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, post) = app
+        .request(Method::GET, &format!("/posts/{post_id}"), None, None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(post["contestId"].is_null());
+}
+
+#[tokio::test]
+async fn deleting_all_contests_detaches_posts_and_drops_likes() {
+    let app = TestApp::new().await;
+    let (_, manager) = app.manager("boss").await;
+    let (_, token) = app.user("alice").await;
+    let contest_id = app.contest(&manager, "contest").await;
+    let (_, body) = app
+        .request(
+            Method::POST,
+            "/posts",
+            Some(&token),
+            Some(json!({
+                "contestId": contest_id,
+                "title": "team",
+                "content": "content",
+                "max": 4,
+                "ppl": 1,
+                "desiredField": 1,
+                "endedAt": 4_000,
+            })),
+        )
+        .await;
+    let post_id = body["postId"].as_i64().unwrap();
+    let (status, _) = app
+        .request(
+            Method::PUT,
+            &format!("/contests/{contest_id}/like"),
+            Some(&token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = app
+        .request(Method::DELETE, "/contests", Some(&manager), None)
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, page) = app.request(Method::GET, "/contests", None, None).await;
+    assert_eq!(page["items"], json!([]));
+    let (_, post) = app
+        .request(Method::GET, &format!("/posts/{post_id}"), None, None)
+        .await;
+    assert!(post["contestId"].is_null());
+    let likes: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM likes")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(likes, 0);
+}
+
+#[tokio::test]
+async fn list_filters_sorts_and_pages() {
+    let app = TestApp::new().await;
+    let (_, manager) = app.manager("boss").await;
+    let create = |title: &'static str, prize: &'static str, started_at: i64, ended_at: i64| {
+        let app = &app;
+        let manager = &manager;
+        async move {
+            let (status, body) = app
+                .request(
+                    Method::POST,
+                    "/contests",
+                    Some(manager),
+                    Some(json!({
+                        "title": title,
+                        "field": 2,
+                        "startedAt": started_at,
+                        "endedAt": ended_at,
+                        "prize": prize,
+                        "link": "https://example.com",
+                        "img": null,
+                        "ratio": "1:1",
+                    })),
+                )
+                .await;
+            assert_eq!(status, StatusCode::CREATED, "{body}");
+            body["contestId"].as_i64().unwrap()
+        }
+    };
+    let closed = app.contest(&manager, "old hackathon").await;
+    let ongoing = create("AI 공모전", "상금 100%", 1_000, 4_000_000_000).await;
+    let upcoming = create(
+        "Design contest",
+        "1,000,000 KRW",
+        3_000_000_000,
+        3_500_000_000,
+    )
+    .await;
+
+    let ids = |query: &str| {
+        let uri = format!("/contests?{query}");
+        let app = &app;
+        async move {
+            let (status, page) = app.request(Method::GET, &uri, None, None).await;
+            assert_eq!(status, StatusCode::OK, "{page}");
+            page["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|contest| contest["contestId"].as_i64().unwrap())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(ids("").await, [closed, upcoming, ongoing]);
+    assert_eq!(ids("sort=start").await, [closed, ongoing, upcoming]);
+    assert_eq!(ids("field=2").await, [upcoming, ongoing]);
+    assert_eq!(ids("status=upcoming").await, [upcoming]);
+    assert_eq!(ids("status=ongoing").await, [ongoing]);
+    assert_eq!(ids("status=closed").await, [closed]);
+    assert_eq!(ids("title=HACK").await, [closed]);
+    assert_eq!(ids("title=%EA%B3%B5%EB%AA%A8").await, [ongoing]);
+    assert_eq!(ids("prize=100%25").await, [ongoing]);
+    assert_eq!(ids("prize=%25").await, [ongoing]);
+
+    let (_, page) = app
+        .request(
+            Method::GET,
+            "/contests?limit=2&includeTotal=true",
+            None,
+            None,
+        )
+        .await;
+    assert_eq!(page["total"], 3);
+    let cursor = page["nextCursor"].as_str().unwrap();
+    assert_eq!(ids(&format!("limit=2&cursor={cursor}")).await, [ongoing]);
+}
+
+async fn image_size(app: &TestApp, url: &Value) -> (u32, u32) {
+    let response = app.fetch(url.as_str().unwrap(), None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let image = image::load_from_memory(&bytes).unwrap();
+    (image.width(), image.height())
+}
+
+#[tokio::test]
+async fn posters_are_checked_against_ratio_and_resized() {
+    let app = TestApp::new().await;
+    let (_, manager) = app.manager("boss").await;
+    let contest = |ratio: &str| {
+        json!({
+            "title": "contest",
+            "field": 1,
+            "startedAt": 1_000,
+            "endedAt": 2_000,
+            "prize": "prize",
+            "link": "https://example.com",
+            "ratio": ratio,
+        })
+        .to_string()
+    };
+
+    let (status, body) = app
+        .multipart(
+            Method::POST,
+            "/contests",
+            &manager,
+            &[
+                ("contest", "application/json", contest("16:9").as_bytes()),
+                ("poster", "image/png", &png(400, 400)),
+            ],
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"]["ratio"], "16:9");
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/contests",
+            Some(&manager),
+            Some(serde_json::from_str(&contest("2:3")).unwrap()),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // 3:4 give or take a pixel is close enough and gets cropped.
+    let (status, body) = app
+        .multipart(
+            Method::POST,
+            "/contests",
+            &manager,
+            &[
+                ("contest", "application/json", contest("3:4").as_bytes()),
+                ("poster", "image/png", &png(480, 641)),
+            ],
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let uri = format!("/contests/{}", body["contestId"]);
+    let (_, contest) = app.request(Method::GET, &uri, None, None).await;
+    assert_eq!(contest["ratio"], "3:4");
+    assert_eq!(contest["img"], contest["poster"]["full"]);
+    assert_eq!(
+        image_size(&app, &contest["poster"]["thumbnail"]).await,
+        (240, 320)
+    );
+    assert_eq!(
+        image_size(&app, &contest["poster"]["card"]).await,
+        (480, 640)
+    );
+    // Smaller than the full size, so only cropped.
+    assert_eq!(
+        image_size(&app, &contest["poster"]["full"]).await,
+        (480, 640)
+    );
+
+    let (status, _) = app
+        .request(
+            Method::PATCH,
+            &uri,
+            Some(&manager),
+            Some(json!({ "ratio": "16:9" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, contest) = app
+        .multipart(
+            Method::PATCH,
+            &uri,
+            &manager,
+            &[
+                ("contest", "application/json", br#"{"ratio":"16:9"}"#),
+                ("poster", "image/png", &png(1600, 900)),
+            ],
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{contest}");
+    assert_eq!(contest["ratio"], "16:9");
+    assert_eq!(
+        image_size(&app, &contest["poster"]["full"]).await,
+        (1200, 675)
+    );
+    assert_eq!(
+        image_size(&app, &contest["poster"]["thumbnail"]).await,
+        (240, 135)
+    );
+
+    let (status, _) = app
+        .request(
+            Method::DELETE,
+            &format!("{uri}/poster"),
+            Some(&manager),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, contest) = app.request(Method::GET, &uri, None, None).await;
+    assert!(contest["poster"].is_null());
+    assert!(contest["img"].is_null());
+}
+
+#[tokio::test]
+async fn posters_stored_in_one_size_fall_back_to_it() {
+    let app = TestApp::new().await;
+    let (_, manager) = app.manager("boss").await;
+    let contest_id = app.contest(&manager, "contest").await;
+    let uri = format!("/contests/{contest_id}");
+    let (status, _) = app
+        .upload(
+            Method::PUT,
+            &format!("{uri}/poster"),
+            &manager,
+            "poster",
+            "image/png",
+            &png(600, 600),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    // What a poster uploaded before migration 0013 looks like.
+    sqlx::query(
+        "UPDATE contests SET poster_card_key = NULL, poster_thumbnail_key = NULL WHERE contest_id = ?",
+    )
+    .bind(contest_id)
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    let (_, contest) = app.request(Method::GET, &uri, None, None).await;
+    assert_eq!(contest["poster"]["full"], contest["img"]);
+    assert_eq!(contest["poster"]["card"], contest["img"]);
+    assert_eq!(contest["poster"]["thumbnail"], contest["img"]);
+    assert_eq!(app.state.clean_up_files().await.unwrap(), 2);
+    assert_eq!(
+        image_size(&app, &contest["poster"]["thumbnail"]).await,
+        (600, 600)
+    );
+}
+
+#[tokio::test]
+async fn free_form_ratios_are_kept_but_not_claimed() {
+    // A database from before migration 0013, when `ratio` was any text.
+    let pool = memory_pool().await;
+    migrate::up(&pool, Some(12)).await.unwrap();
+    sqlx::query(
+        "INSERT INTO users (id, username, password, nickname, email, is_manager, is_withdrawn) \
+         VALUES (1, 'boss', 'boss-password', 'boss', 'boss@example.com', TRUE, FALSE)",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    for (contest_id, ratio) in [(1, "A4"), (2, "wide"), (3, "1:1")] {
+        sqlx::query(
+            "INSERT INTO contests (contest_id, user_id, title, ratio, prize, started_at, ended_at, link, field, like_count) \
+             VALUES (?, 1, 'contest', ?, 'prize', 1000, 2000, 'https://example.com', 1, 0)",
+        )
+        .bind(contest_id)
+        .bind(ratio)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let app = TestApp::with_pool(pool).await;
+    let manager = app.login("boss").await;
+    let (_, contest) = app.request(Method::GET, "/contests/1", None, None).await;
+    assert!(contest["ratio"].is_null());
+    let (_, contest) = app.request(Method::GET, "/contests/3", None, None).await;
+    assert_eq!(contest["ratio"], "1:1");
+
+    // A poster needs a ratio to be checked against.
+    let (status, _) = app
+        .upload(
+            Method::PUT,
+            "/contests/2/poster",
+            &manager,
+            "poster",
+            "image/png",
+            &png(1600, 900),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, contest) = app
+        .request(
+            Method::PATCH,
+            "/contests/2",
+            Some(&manager),
+            Some(json!({ "ratio": "16:9" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(contest["ratio"], "16:9");
+
+    // Reverting 0013 gives contests without a ratio their original text back.
+    let since_0013 = migrate::status(&app.pool)
+        .await
+        .unwrap()
+        .iter()
+        .filter(|status| status.version >= 13)
+        .count();
+    migrate::down(&app.pool, since_0013).await.unwrap();
+    let ratios: Vec<String> = sqlx::query_scalar("SELECT ratio FROM contests ORDER BY contest_id")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(ratios, ["A4", "16:9", "1:1"]);
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use std::fs;
+
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+};
+use tokio::task::JoinSet;
+use tower::ServiceExt;
+
+use common::{file_pool, TestApp};
+
+#[tokio::test]
+async fn likes_are_idempotent_and_counted() {
+    let app = TestApp::new().await;
+    let (_, alice) = app.user("alice").await;
+    let (_, bob) = app.user("bob").await;
+    let post_id = app.post(&alice, "post").await;
+    let uri = format!("/posts/{post_id}/like");
+
+    let (status, _) = app.request(Method::PUT, &uri, None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    for _ in 0..2 {
+        let (status, body) = app.request(Method::PUT, &uri, Some(&alice), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["likeCount"], 1);
+        assert_eq!(body["likedByMe"], true);
+    }
+    let (_, body) = app.request(Method::PUT, &uri, Some(&bob), None).await;
+    assert_eq!(body["likeCount"], 2);
+
+    // The flag only appears for signed-in requests.
+    let post_uri = format!("/posts/{post_id}");
+    let (_, post) = app.request(Method::GET, &post_uri, None, None).await;
+    assert_eq!(post["likeCount"], 2);
+    assert!(post.get("likedByMe").is_none());
+    let (_, post) = app.request(Method::GET, &post_uri, Some(&bob), None).await;
+    assert_eq!(post["likedByMe"], true);
+
+    for _ in 0..2 {
+        let (_, body) = app.request(Method::DELETE, &uri, Some(&bob), None).await;
+        assert_eq!(body["likeCount"], 1);
+        assert_eq!(body["likedByMe"], false);
+    }
+    let (_, page) = app.request(Method::GET, "/posts", Some(&bob), None).await;
+    assert_eq!(page["items"][0]["likedByMe"], false);
+    let (_, page) = app
+        .request(Method::GET, "/posts?sort=mostLiked", Some(&alice), None)
+        .await;
+    assert_eq!(page["items"][0]["likedByMe"], true);
+
+    let (status, _) = app
+        .request(Method::PUT, "/posts/999/like", Some(&alice), None)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let orphans: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM likes WHERE item_id = 999")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(orphans, 0);
+    let (status, _) = app.request(Method::GET, &post_uri, Some("bad"), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn contest_likes_and_deletion() {
+    let app = TestApp::new().await;
+    let (_, manager) = app.manager("boss").await;
+    let (_, alice) = app.user("alice").await;
+    let contest_id = app.contest(&manager, "contest").await;
+    let uri = format!("/contests/{contest_id}/like");
+
+    let (_, body) = app.request(Method::PUT, &uri, Some(&alice), None).await;
+    assert_eq!(body["likeCount"], 1);
+    let (_, page) = app
+        .request(Method::GET, "/contests", Some(&alice), None)
+        .await;
+    assert_eq!(page["items"][0]["likedByMe"], true);
+    assert_eq!(page["items"][0]["likeCount"], 1);
+
+    let (status, _) = app
+        .request(
+            Method::DELETE,
+            &format!("/contests/{contest_id}"),
+            Some(&manager),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let likes: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM likes")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(likes, 0);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_likes_are_all_counted() {
+    let (pool, path) = file_pool().await;
+    let app = TestApp::with_pool(pool).await;
+    let (_, author) = app.user("author").await;
+    let post_id = app.post(&author, "post").await;
+    let mut tokens = Vec::new();
+    for i in 0..16 {
+        tokens.push(app.user(&format!("user{i}")).await.1);
+    }
+
+    let mut likes = JoinSet::new();
+    for token in tokens {
+        let router = app.router.clone();
+        let request = Request::put(format!("/posts/{post_id}/like"))
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .body(Body::empty())
+            .unwrap();
+        likes.spawn(async move { router.oneshot(request).await.unwrap().status() });
+    }
+    while let Some(status) = likes.join_next().await {
+        assert_eq!(status.unwrap(), StatusCode::OK);
+    }
+
+    let (_, post) = app
+        .request(Method::GET, &format!("/posts/{post_id}"), None, None)
+        .await;
+    assert_eq!(post["likeCount"], 16);
+
+    app.pool.close().await;
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = fs::remove_file(format!("{}{suffix}", path.display()));
+    }
+}
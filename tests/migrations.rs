@@ -0,0 +1,216 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use sagongsa_server::migrate;
+use serde_json::json;
+use sqlx::Executor;
+
+use common::{memory_pool, TestApp};
+
+/// The schema `init_tables` created before migrations existed.
+const BASELINE_SCHEMA: &str = r#"
+    CREATE TABLE IF NOT EXISTS users (
+        id INTEGER PRIMARY KEY,
+        username VARCHAR(10) NOT NULL,
+        password VARCHAR(20) NOT NULL,
+        nickname VARCHAR(10) NOT NULL,
+        email VARCHAR(100) NOT NULL,
+        bio VARCHAR(1000),
+        is_manager BOOLEAN NOT NULL,
+        is_withdrawn BOOLEAN NOT NULL,
+        field INTEGER,
+        profile_img BLOB
+    );
+
+    CREATE TABLE IF NOT EXISTS contests (
+        contest_id INTEGER PRIMARY KEY,
+        user_id INTEGER NOT NULL,
+        title VARCHAR(100) NOT NULL,
+        img VARCHAR(1000),
+        ratio VARCHAR(100) NOT NULL,
+        prize VARCHAR(100) NOT NULL,
+        started_at DATETIME NOT NULL,
+        ended_at DATETIME NOT NULL,
+        link VARCHAR(1000) NOT NULL,
+        field INTEGER NOT NULL,
+        like_count INTEGER NOT NULL,
+        FOREIGN KEY (user_id) REFERENCES users(id)
+    );
+
+    CREATE TABLE IF NOT EXISTS posts (
+        post_id INTEGER PRIMARY KEY,
+        user_id INTEGER NOT NULL,
+        contest_id INTEGER,
+        title VARCHAR(100) NOT NULL,
+        content VARCHAR(1000),
+        max INTEGER,
+        ppl INTEGER,
+        desired_field INTEGER,
+        created_at DATETIME NOT NULL,
+        ended_at DATETIME NOT NULL,
+        like_count INTEGER NOT NULL,
+        FOREIGN KEY (user_id) REFERENCES users(id),
+        FOREIGN KEY (contest_id) REFERENCES contests(contest_id)
+    );
+
+    CREATE TABLE IF NOT EXISTS comments (
+        comment_id INTEGER PRIMARY KEY,
+        post_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        content VARCHAR NOT NULL,
+        created_at DATETIME NOT NULL,
+        edited_at DATETIME,
+        parent INTEGER,
+        FOREIGN KEY (post_id) REFERENCES posts(post_id),
+        FOREIGN KEY (user_id) REFERENCES users(id)
+    );
+"#;
+
+/// A fresh database with the baseline schema and one plaintext-password user, `legacy`.
+async fn baseline_app() -> TestApp {
+    let pool = memory_pool().await;
+    pool.execute(BASELINE_SCHEMA).await.unwrap();
+    pool.execute(
+        "INSERT INTO users (username, password, nickname, email, is_manager, is_withdrawn) \
+         VALUES ('legacy', 'legacy-password', 'legacy', 'legacy@example.com', FALSE, FALSE)",
+    )
+    .await
+    .unwrap();
+    TestApp::with_pool(pool).await
+}
+
+async fn table_exists(app: &TestApp, name: &str) -> bool {
+    sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?",
+    )
+    .bind(name)
+    .fetch_one(&app.pool)
+    .await
+    .unwrap()
+        == 1
+}
+
+#[tokio::test]
+async fn all_migrations_applied_on_startup() {
+    let app = TestApp::new().await;
+
+    let statuses = migrate::status(&app.pool).await.unwrap();
+    assert!(!statuses.is_empty());
+    assert!(statuses.iter().all(|status| status.applied_at.is_some()));
+    assert!(migrate::up(&app.pool, None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn down_and_up_round_trip() {
+    let app = TestApp::new().await;
+    let count = migrate::status(&app.pool).await.unwrap().len();
+
+    let reverted = migrate::down(&app.pool, count).await.unwrap();
+    assert_eq!(reverted.len(), count);
+    assert!(!table_exists(&app, "users").await);
+    assert!(migrate::status(&app.pool)
+        .await
+        .unwrap()
+        .iter()
+        .all(|status| status.applied_at.is_none()));
+
+    let applied = migrate::up(&app.pool, Some(1)).await.unwrap();
+    assert_eq!(applied.len(), 1);
+    migrate::up(&app.pool, None).await.unwrap();
+    assert!(table_exists(&app, "users").await);
+}
+
+#[tokio::test]
+async fn upgraded_baseline_rejects_duplicate_users() {
+    let app = baseline_app().await;
+
+    for (username, nickname, email) in [
+        ("legacy", "other", "other@example.com"),
+        ("other", "other", "legacy@example.com"),
+    ] {
+        let (status, body) = app
+            .request(
+                Method::POST,
+                "/signup",
+                None,
+                Some(json!({
+                    "username": username,
+                    "password": "password",
+                    "nickname": nickname,
+                    "email": email,
+                })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    }
+    app.signup("other").await;
+}
+
+#[tokio::test]
+async fn upgraded_baseline_rejects_taken_email_changes() {
+    let app = baseline_app().await;
+    let (_, token) = app.user("other").await;
+
+    let (status, body) = app
+        .request(
+            Method::PUT,
+            "/users/@me/email",
+            Some(&token),
+            Some(json!({
+                "currentPassword": "other-password",
+                "email": "legacy@example.com",
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    let (_, me) = app
+        .request(Method::GET, "/users/@me", Some(&token), None)
+        .await;
+    assert_eq!(me["email"], "other@example.com");
+}
+
+#[tokio::test]
+async fn legacy_plaintext_passwords_are_rehashed_on_login() {
+    let app = baseline_app().await;
+    let login = |password: &'static str| {
+        app.request(
+            Method::POST,
+            "/login",
+            None,
+            Some(json!({ "username": "legacy", "password": password })),
+        )
+    };
+    let stored_password = || async {
+        sqlx::query_scalar::<_, String>("SELECT password FROM users WHERE username = 'legacy'")
+            .fetch_one(&app.pool)
+            .await
+            .unwrap()
+    };
+
+    assert_eq!(login("wrong").await.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(stored_password().await, "legacy-password");
+
+    let (status, body) = login("legacy-password").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(stored_password().await.starts_with("$argon2id$"));
+    assert_eq!(login("legacy-password").await.0, StatusCode::OK);
+    assert_eq!(login("wrong").await.0, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn upgraded_baseline_has_the_same_indexes_as_a_fresh_database() {
+    async fn indexes(app: &TestApp) -> Vec<(String, String, String)> {
+        sqlx::query_as(
+            "SELECT m.name, i.name, i.\"unique\" || i.origin || i.partial \
+             FROM sqlite_master AS m, pragma_index_list(m.name) AS i \
+             WHERE m.type = 'table' ORDER BY m.name, i.name",
+        )
+        .fetch_all(&app.pool)
+        .await
+        .unwrap()
+    }
+
+    let fresh = TestApp::new().await;
+    let upgraded = baseline_app().await;
+    assert_eq!(indexes(&fresh).await, indexes(&upgraded).await);
+}
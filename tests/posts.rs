@@ -0,0 +1,272 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+use common::TestApp;
+
+#[tokio::test]
+async fn author_can_patch_selected_fields() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+    let post_id = app.post(&token, "before").await;
+
+    let (status, post) = app
+        .request(
+            Method::PATCH,
+            &format!("/posts/{post_id}"),
+            Some(&token),
+            Some(json!({ "title": "after", "max": 6 })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(post["title"], "after");
+    assert_eq!(post["max"], 6);
+    assert_eq!(post["content"], "content");
+    assert_eq!(post["likedByMe"], false);
+    assert_eq!(post["bookmarkedByMe"], false);
+
+    let (status, _) = app
+        .request(
+            Method::PATCH,
+            &format!("/posts/{post_id}"),
+            Some(&token),
+            Some(json!({ "max": 0 })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn create_rejects_impossible_member_counts() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+
+    for (max, ppl, field) in [(0, 0, "max"), (-1, 0, "max"), (2, 3, "ppl"), (2, -1, "ppl")] {
+        let (status, body) = app
+            .request(
+                Method::POST,
+                "/posts",
+                Some(&token),
+                Some(json!({
+                    "title": "post",
+                    "content": "content",
+                    "max": max,
+                    "ppl": ppl,
+                    "desiredField": 1,
+                    "endedAt": 4_000_000_000_i64,
+                })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+        assert!(body["details"]["fields"][field].is_string(), "{body}");
+    }
+}
+
+#[tokio::test]
+async fn only_author_or_manager_can_modify() {
+    let app = TestApp::new().await;
+    let (_, author) = app.user("alice").await;
+    let (_, other) = app.user("bob").await;
+    let (_, manager) = app.manager("boss").await;
+    let post_id = app.post(&author, "post").await;
+    let uri = format!("/posts/{post_id}");
+
+    let (status, _) = app
+        .request(
+            Method::PATCH,
+            &uri,
+            Some(&other),
+            Some(json!({ "title": "hijacked" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = app.request(Method::DELETE, &uri, Some(&other), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = app
+        .request(Method::DELETE, &uri, Some(&manager), None)
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) = app.request(Method::GET, &uri, None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn deleting_post_removes_its_comments() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+    let post_id = app.post(&token, "post").await;
+    let parent = app.comment(&token, post_id, None).await;
+    app.comment(&token, post_id, Some(parent)).await;
+
+    let (status, _) = app
+        .request(
+            Method::DELETE,
+            &format!("/posts/{post_id}"),
+            Some(&token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM comments")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, 0);
+}
+
+#[tokio::test]
+async fn list_pages_through_cursor() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+    let mut ids = Vec::new();
+    for i in 0..5 {
+        ids.push(app.post(&token, &format!("post {i}")).await);
+    }
+    ids.reverse();
+
+    let mut seen = Vec::new();
+    let mut uri = "/posts?limit=2&includeTotal=true".to_string();
+    loop {
+        let (status, page) = app.request(Method::GET, &uri, None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["total"], 5);
+        seen.extend(
+            page["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|post| post["postId"].as_i64().unwrap()),
+        );
+        match page["nextCursor"].as_str() {
+            Some(cursor) => uri = format!("/posts?limit=2&includeTotal=true&cursor={cursor}"),
+            None => break,
+        }
+    }
+    assert_eq!(seen, ids);
+
+    let (_, page) = app.request(Method::GET, "/posts?limit=2", None, None).await;
+    assert!(page.get("total").is_none());
+    let cursor = page["nextCursor"].as_str().unwrap();
+    let (status, _) = app
+        .request(
+            Method::GET,
+            &format!("/posts?sort=deadline&cursor={cursor}"),
+            None,
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = app
+        .request(Method::GET, "/posts?cursor=garbage", None, None)
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn list_filters_and_sorts() {
+    let app = TestApp::new().await;
+    let (alice_id, alice) = app.user("alice").await;
+    let (_, bob) = app.user("bob").await;
+    let soon = app.post(&alice, "soon").await;
+    let later = app.post(&alice, "later").await;
+    let closed = app.post(&bob, "closed").await;
+
+    let patch = |post_id: i64, body| {
+        let token = if post_id == closed { &bob } else { &alice };
+        let uri = format!("/posts/{post_id}");
+        let app = &app;
+        async move {
+            app.request(Method::PATCH, &uri, Some(token), Some(body))
+                .await
+        }
+    };
+    patch(
+        soon,
+        json!({ "endedAt": 3_000_000_000_i64, "desiredField": 2 }),
+    )
+    .await;
+    patch(closed, json!({ "endedAt": 1 })).await;
+    sqlx::query("UPDATE posts SET like_count = 7 WHERE post_id = ?")
+        .bind(later)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let ids = |page: &serde_json::Value| {
+        page["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|post| post["postId"].as_i64().unwrap())
+            .collect::<Vec<_>>()
+    };
+    let get = |query: &str| {
+        let uri = format!("/posts?{query}");
+        let app = &app;
+        async move { app.request(Method::GET, &uri, None, None).await.1 }
+    };
+
+    assert_eq!(
+        ids(&get(&format!("author={alice_id}")).await),
+        [later, soon]
+    );
+    assert_eq!(ids(&get("desiredField=2").await), [soon]);
+    assert_eq!(ids(&get("status=open&sort=deadline").await), [soon, later]);
+    assert_eq!(ids(&get("status=closed").await), [closed]);
+    assert_eq!(ids(&get("sort=mostLiked").await)[0], later);
+    assert_eq!(ids(&get("createdBefore=1").await), Vec::<i64>::new());
+}
+
+#[tokio::test]
+async fn malformed_requests_get_json_errors() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+
+    for (method, uri, body, status, code) in [
+        (
+            Method::POST,
+            "/posts",
+            None,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "unsupported_media_type",
+        ),
+        (
+            Method::POST,
+            "/posts",
+            Some(json!({ "title": 1 })),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "unprocessable",
+        ),
+        (
+            Method::GET,
+            "/posts/abc",
+            None,
+            StatusCode::BAD_REQUEST,
+            "bad_request",
+        ),
+        (
+            Method::GET,
+            "/posts?limit=many",
+            None,
+            StatusCode::BAD_REQUEST,
+            "bad_request",
+        ),
+        (
+            Method::GET,
+            "/no-such-route",
+            None,
+            StatusCode::NOT_FOUND,
+            "not_found",
+        ),
+    ] {
+        let (actual, response) = app.request(method, uri, Some(&token), body).await;
+        assert_eq!(actual, status, "{uri}: {response}");
+        assert_eq!(response["code"], code, "{uri}");
+        assert!(response["message"].is_string(), "{uri}");
+    }
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use common::TestApp;
+
+async fn search(app: &TestApp, query: &str) -> Value {
+    let (status, body) = app
+        .request(Method::GET, &format!("/search?{query}"), None, None)
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    body
+}
+
+fn ids(hits: &Value, key: &str) -> Vec<i64> {
+    hits.as_array()
+        .unwrap()
+        .iter()
+        .map(|hit| hit[key].as_i64().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn finds_korean_text_and_stays_in_sync() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+    let post_id = app.post(&token, "백엔드 개발자 구합니다").await;
+    let other = app.post(&token, "디자이너 모집").await;
+    let comment_id = app.comment(&token, other, None).await;
+    app.request(
+        Method::PATCH,
+        &format!("/posts/{other}/comments/{comment_id}"),
+        Some(&token),
+        Some(json!({ "content": "저도 백엔드 개발 가능합니다" })),
+    )
+    .await;
+
+    // Three characters or more go through the trigram index.
+    let hits = search(&app, "q=%EB%B0%B1%EC%97%94%EB%93%9C").await;
+    assert_eq!(ids(&hits["posts"], "postId"), [post_id]);
+    assert_eq!(ids(&hits["comments"], "commentId"), [comment_id]);
+    assert_eq!(hits["comments"][0]["postId"], other);
+    assert!(hits["posts"][0]["score"].as_f64().unwrap() > 0.0);
+    assert_eq!(
+        hits["comments"][0]["snippet"],
+        "저도 <mark>백엔드</mark> 개발 가능합니다"
+    );
+
+    // Two-syllable words are shorter than a trigram and still match.
+    let hits = search(&app, "q=%EB%AA%A8%EC%A7%91&type=posts").await;
+    assert_eq!(ids(&hits["posts"], "postId"), [other]);
+    assert!(hits["comments"].as_array().unwrap().is_empty());
+
+    app.request(
+        Method::PATCH,
+        &format!("/posts/{post_id}"),
+        Some(&token),
+        Some(json!({ "title": "프론트엔드 개발자 구합니다" })),
+    )
+    .await;
+    app.request(
+        Method::DELETE,
+        &format!("/posts/{other}/comments/{comment_id}"),
+        Some(&token),
+        None,
+    )
+    .await;
+    let hits = search(&app, "q=%EB%B0%B1%EC%97%94%EB%93%9C").await;
+    assert!(hits["posts"].as_array().unwrap().is_empty());
+    assert!(hits["comments"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn ranks_and_escapes_snippets() {
+    let app = TestApp::new().await;
+    let (_, manager) = app.manager("boss").await;
+    let weak = app.contest(&manager, "hackathon").await;
+    let strong = app
+        .contest(&manager, "Hackathon hackathon <b>hackathon</b>")
+        .await;
+
+    let hits = search(&app, "q=HACKATHON&type=contests").await;
+    assert_eq!(ids(&hits["contests"], "contestId"), [strong, weak]);
+    assert_eq!(
+        hits["contests"][0]["snippet"],
+        "<mark>Hackathon</mark> <mark>hackathon</mark> &lt;b&gt;<mark>hackathon</mark>&lt;/b&gt;"
+    );
+
+    // FTS5 syntax in the query is matched literally instead of failing.
+    let hits = search(&app, "q=hack%22+OR&type=contests").await;
+    assert!(hits["contests"].as_array().unwrap().is_empty());
+
+    let (status, _) = app.request(Method::GET, "/search?q=+", None, None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
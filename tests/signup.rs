@@ -0,0 +1,222 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+use common::TestApp;
+
+#[tokio::test]
+async fn signup_returns_profile_and_tokens() {
+    let app = TestApp::new().await;
+
+    let body = app.signup("alice").await;
+    assert_eq!(body["user"]["username"], "alice");
+    assert_eq!(body["user"]["nickname"], "alice");
+    assert!(body["user"].get("password").is_none());
+    assert!(body["user"].get("email").is_none());
+    assert!(body["refreshToken"].is_string());
+
+    let token = body["token"].as_str().unwrap();
+    let (status, me) = app
+        .request(Method::GET, "/users/@me", Some(token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["id"], body["user"]["id"]);
+}
+
+#[tokio::test]
+async fn signup_stores_hashed_password() {
+    let app = TestApp::new().await;
+    app.signup("alice").await;
+
+    let password: String = sqlx::query_scalar("SELECT password FROM users WHERE username = ?")
+        .bind("alice")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert!(password.starts_with("$argon2id$"));
+}
+
+#[tokio::test]
+async fn signup_rejects_duplicate_username() {
+    let app = TestApp::new().await;
+    app.signup("alice").await;
+
+    let (status, body) = app
+        .request(
+            Method::POST,
+            "/signup",
+            None,
+            Some(json!({
+                "username": "alice",
+                "password": "other",
+                "nickname": "other",
+                "email": "other@example.com",
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "conflict");
+}
+
+#[tokio::test]
+async fn signup_rejects_duplicate_email() {
+    let app = TestApp::new().await;
+    app.signup("alice").await;
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/signup",
+            None,
+            Some(json!({
+                "username": "bob",
+                "password": "other",
+                "nickname": "bob",
+                "email": "alice@example.com",
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn login_after_signup() {
+    let app = TestApp::new().await;
+    app.signup("alice").await;
+
+    let (status, body) = app
+        .request(
+            Method::POST,
+            "/login",
+            None,
+            Some(json!({ "username": "alice", "password": "alice-password" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["token"].is_string());
+
+    let (status, body) = app
+        .request(
+            Method::POST,
+            "/login",
+            None,
+            Some(json!({ "username": "alice", "password": "wrong" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], "unauthorized");
+}
+
+#[tokio::test]
+async fn signup_validates_fields_and_accepts_field() {
+    let app = TestApp::new().await;
+
+    let (status, body) = app
+        .request(
+            Method::POST,
+            "/signup",
+            None,
+            Some(json!({
+                "username": "much-too-long",
+                "password": "password",
+                "nickname": " ",
+                "email": "nope",
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let fields = body["details"]["fields"].as_object().unwrap();
+    assert_eq!(fields.len(), 3);
+
+    let (status, body) = app
+        .request(
+            Method::POST,
+            "/signup",
+            None,
+            Some(json!({
+                "username": "alice",
+                "password": "password",
+                "nickname": "alice",
+                "email": "alice@example.com",
+                "field": 2,
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["user"]["field"], 2);
+}
+
+#[tokio::test]
+async fn signup_stores_trimmed_names_and_lowercase_email() {
+    let app = TestApp::new().await;
+    app.signup("alice").await;
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/signup",
+            None,
+            Some(json!({
+                "username": "alice ",
+                "password": "other",
+                "nickname": "other",
+                "email": "other@example.com",
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/signup",
+            None,
+            Some(json!({
+                "username": "bob",
+                "password": "other",
+                "nickname": "bob",
+                "email": "Alice@Example.com",
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = app
+        .request(
+            Method::POST,
+            "/signup",
+            None,
+            Some(json!({
+                "username": " bob",
+                "password": "bob-password",
+                "nickname": "bob ",
+                "email": " Bob@Example.com ",
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    assert_eq!(body["user"]["username"], "bob");
+    assert_eq!(body["user"]["nickname"], "bob");
+    let token = app.login("bob").await;
+    let (_, me) = app
+        .request(Method::GET, "/users/@me", Some(&token), None)
+        .await;
+    assert_eq!(me["email"], "bob@example.com");
+}
+
+#[tokio::test]
+async fn login_trims_the_username_like_signup() {
+    let app = TestApp::new().await;
+    app.signup("alice").await;
+
+    let (status, body) = app
+        .request(
+            Method::POST,
+            "/login",
+            None,
+            Some(json!({ "username": " alice ", "password": "alice-password" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["token"].is_string());
+}
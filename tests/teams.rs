@@ -0,0 +1,167 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use common::TestApp;
+
+fn member_ids(team: &Value) -> Vec<i64> {
+    team["members"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|member| member["userId"].as_i64().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn team_is_formed_from_accepted_applicants() {
+    let app = TestApp::new().await;
+    let (alice_id, alice) = app.user("alice").await;
+    let (bob_id, bob) = app.user("bob").await;
+    let (carol_id, carol) = app.user("carol").await;
+    let (_, manager) = app.manager("boss").await;
+    let contest_id = app.contest(&manager, "contest").await;
+    let (_, post) = app
+        .request(
+            Method::POST,
+            "/posts",
+            Some(&alice),
+            Some(json!({
+                "contestId": contest_id,
+                "title": "our team",
+                "content": "content",
+                "max": 4,
+                "ppl": 1,
+                "desiredField": 1,
+                "endedAt": 4_000_000_000_i64,
+            })),
+        )
+        .await;
+    let post_id = post["postId"].as_i64().unwrap();
+
+    let (_, application) = app.apply(&bob, post_id).await;
+    app.decide(&alice, post_id, &application, "accept").await;
+
+    let uri = format!("/posts/{post_id}/team");
+    let (status, _) = app
+        .request(Method::POST, &uri, Some(&bob), Some(json!({})))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, team) = app
+        .request(Method::POST, &uri, Some(&alice), Some(json!({})))
+        .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(team["name"], "our team");
+    assert_eq!(team["contestId"], contest_id);
+    assert_eq!(team["members"][0]["role"], "leader");
+    assert_eq!(member_ids(&team), [alice_id, bob_id]);
+    let (status, _) = app
+        .request(Method::POST, &uri, Some(&alice), Some(json!({})))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Accepting after the team exists adds the applicant to it.
+    let (_, application) = app.apply(&carol, post_id).await;
+    app.decide(&alice, post_id, &application, "accept").await;
+    let (_, teams) = app
+        .request(Method::GET, &format!("/users/{carol_id}/teams"), None, None)
+        .await;
+    assert_eq!(teams.as_array().unwrap().len(), 1);
+    assert_eq!(member_ids(&teams[0]), [alice_id, bob_id, carol_id]);
+
+    // The team outlives its post and contest.
+    app.request(
+        Method::DELETE,
+        &format!("/posts/{post_id}"),
+        Some(&alice),
+        None,
+    )
+    .await;
+    app.request(
+        Method::DELETE,
+        &format!("/contests/{contest_id}"),
+        Some(&manager),
+        None,
+    )
+    .await;
+    let team_id = team["teamId"].as_i64().unwrap();
+    let (status, team) = app
+        .request(Method::GET, &format!("/teams/{team_id}"), None, None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(team["postId"], Value::Null);
+    assert_eq!(team["contestId"], Value::Null);
+}
+
+#[tokio::test]
+async fn leave_transfer_and_disband() {
+    let app = TestApp::new().await;
+    let (_, alice) = app.user("alice").await;
+    let (bob_id, bob) = app.user("bob").await;
+    let (_, carol) = app.user("carol").await;
+    let post_id = app.post(&alice, "team").await;
+    for token in [&bob, &carol] {
+        let (_, application) = app.apply(token, post_id).await;
+        app.decide(&alice, post_id, &application, "accept").await;
+    }
+    let (_, team) = app
+        .request(
+            Method::POST,
+            &format!("/posts/{post_id}/team"),
+            Some(&alice),
+            Some(json!({ "name": "Renamed" })),
+        )
+        .await;
+    assert_eq!(team["name"], "Renamed");
+    let team_id = team["teamId"].as_i64().unwrap();
+    let leave = format!("/teams/{team_id}/members/@me");
+    let leader = format!("/teams/{team_id}/leader");
+
+    let (status, _) = app
+        .request(Method::DELETE, &leave, Some(&alice), None)
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = app
+        .request(Method::DELETE, &leave, Some(&carol), None)
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = app
+        .request(Method::DELETE, &leave, Some(&carol), None)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = app
+        .request(
+            Method::PUT,
+            &leader,
+            Some(&bob),
+            Some(json!({ "userId": bob_id })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, team) = app
+        .request(
+            Method::PUT,
+            &leader,
+            Some(&alice),
+            Some(json!({ "userId": bob_id })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(team["members"][0]["userId"], bob_id);
+    assert_eq!(team["members"][1]["role"], "member");
+
+    // Alice is an ordinary member now and can leave; Bob disbands.
+    let (status, _) = app
+        .request(Method::DELETE, &leave, Some(&alice), None)
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let uri = format!("/teams/{team_id}");
+    let (status, _) = app.request(Method::DELETE, &uri, Some(&alice), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.request(Method::DELETE, &uri, Some(&bob), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = app.request(Method::GET, &uri, None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,174 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use common::TestApp;
+
+#[tokio::test]
+async fn public_profiles_hide_private_fields() {
+    let app = TestApp::new().await;
+    let (id, _) = app.user("alice").await;
+
+    let (status, users) = app.request(Method::GET, "/users", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, user) = app
+        .request(Method::GET, &format!("/users/{id}"), None, None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    for user in [&users[0], &user] {
+        assert_eq!(user["username"], "alice");
+        assert!(user.get("password").is_none());
+        assert!(user.get("email").is_none());
+        assert!(user["avatarUrl"].is_null());
+    }
+}
+
+#[tokio::test]
+async fn me_includes_email_but_not_password() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+
+    let (status, me) = app
+        .request(Method::GET, "/users/@me", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["email"], "alice@example.com");
+    assert_eq!(me["isManager"], false);
+    assert!(me.get("password").is_none());
+}
+
+#[tokio::test]
+async fn patch_me_validates_and_saves_profile() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+    app.user("bob").await;
+
+    let (status, me) = app
+        .request(
+            Method::PATCH,
+            "/users/@me",
+            Some(&token),
+            Some(json!({ "nickname": "앨리스", "bio": "hi", "field": 3 })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["nickname"], "앨리스");
+    assert_eq!(me["bio"], "hi");
+    assert_eq!(me["field"], 3);
+
+    let (status, body) = app
+        .request(
+            Method::PATCH,
+            "/users/@me",
+            Some(&token),
+            Some(json!({ "nickname": "x".repeat(11), "bio": "x".repeat(1001) })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body["details"]["fields"]["nickname"].is_string());
+    assert!(body["details"]["fields"]["bio"].is_string());
+
+    let (status, _) = app
+        .request(
+            Method::PATCH,
+            "/users/@me",
+            Some(&token),
+            Some(json!({ "nickname": "bob" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    // Surrounding whitespace is dropped, so it cannot make a taken nickname look new.
+    let (status, _) = app
+        .request(
+            Method::PATCH,
+            "/users/@me",
+            Some(&token),
+            Some(json!({ "nickname": " bob " })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (_, me) = app
+        .request(
+            Method::PATCH,
+            "/users/@me",
+            Some(&token),
+            Some(json!({ "bio": "" })),
+        )
+        .await;
+    assert_eq!(me["bio"], Value::Null);
+    assert_eq!(me["nickname"], "앨리스");
+}
+
+#[tokio::test]
+async fn password_and_email_changes_need_current_password() {
+    let app = TestApp::new().await;
+    let (_, token) = app.user("alice").await;
+    let other_session = app.login("alice").await;
+    app.user("bob").await;
+
+    let (status, _) = app
+        .request(
+            Method::PUT,
+            "/users/@me/password",
+            Some(&token),
+            Some(json!({ "currentPassword": "wrong", "newPassword": "new-password" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app
+        .request(
+            Method::PUT,
+            "/users/@me/password",
+            Some(&token),
+            Some(json!({ "currentPassword": "alice-password", "newPassword": "new-password" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // Other sessions are signed out; the one that made the change stays.
+    let (status, _) = app
+        .request(Method::GET, "/users/@me", Some(&other_session), None)
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/login",
+            None,
+            Some(json!({ "username": "alice", "password": "new-password" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let change_email = |password: &'static str, email: &'static str| {
+        let app = &app;
+        let token = &token;
+        async move {
+            app.request(
+                Method::PUT,
+                "/users/@me/email",
+                Some(token),
+                Some(json!({ "currentPassword": password, "email": email })),
+            )
+            .await
+        }
+    };
+    assert_eq!(
+        change_email("alice-password", "a@new.com").await.0,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        change_email("new-password", "not-an-email").await.0,
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+    assert_eq!(
+        change_email("new-password", " Bob@Example.com").await.0,
+        StatusCode::CONFLICT
+    );
+    let (status, me) = change_email("new-password", "A@New.com ").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["email"], "a@new.com");
+}